@@ -1,13 +1,16 @@
 use applydiff_core::{
-    apply::Applier,
+    apply::{run_by_file, Applier, ToleratedLine},
     backup,
+    config::ProjectConfig,
     error::Result as PatchResult,
+    locate::Relocation,
     logger::Logger,
-    parse::Parser,
+    parse::{render_patch, Parser},
+    r#match::AssumedSpan,
 };
 use chrono::Local;
-use serde::Serialize;
-use similar::TextDiff;
+use serde::{Deserialize, Serialize};
+use similar::{ChangeTag, TextDiff};
 use std::fs;
 use std::path::PathBuf;
 use tauri_plugin_dialog::{DialogExt, FilePath};
@@ -18,6 +21,44 @@ const MAX_INPUT_SIZE: usize = 100_000_000;
 pub struct PreviewResult {
     pub log: String,
     pub diff: String,
+    /// Blocks whose FROM only matched in another file; applied there once confirmed
+    pub relocations: Vec<RelocationView>,
+    /// Failed blocks that `preview_block` can retry with a lower threshold
+    pub retries: Vec<FuzzRetry>,
+    /// Blocks that matched, rewritten with the exact file text as FROM (empty if none did)
+    pub canonical: String,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct RelocationView {
+    pub named: String,
+    pub found: String,
+}
+
+/// A failed block that would match at a lower fuzz threshold
+#[derive(Serialize, Deserialize, Clone)]
+pub struct FuzzRetry {
+    /// 0-based index of the block in the patch
+    pub block: usize,
+    pub fuzz: f64,
+}
+
+/// What `apply_patch` did, so the app branches on the outcome rather than the log text
+#[derive(Serialize)]
+pub struct ApplyOutcome {
+    pub status: ApplyStatus,
+    pub log: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApplyStatus {
+    /// The commit wrote every changed file
+    Written,
+    /// Blocks failed and a partial apply was not allowed; no file was touched
+    NothingWritten,
+    /// Writing failed part-way; replaced files were rolled back where possible
+    Failed,
 }
 
 /* ========================== Commands ========================== */
@@ -43,18 +84,56 @@ pub fn run_self_test() -> String {
 }
 
 #[tauri::command]
-pub fn preview_patch(target: String, patch: String) -> Result<PreviewResult, String> {
-    preview_patch_impl(&target, &patch).map_err(|e| e.to_string())
+pub fn preview_patch(
+    target: String,
+    patch: String,
+    relocations: Option<Vec<RelocationView>>,
+) -> Result<PreviewResult, String> {
+    preview_patch_impl(&target, &patch, &relocations.unwrap_or_default(), &[], None).map_err(|e| e.to_string())
+}
+
+/// Preview `patch` again with one block at the threshold a previous preview suggested,
+/// reporting that block alone; the blocks around it run as they will at apply time,
+/// including the thresholds already `accepted` for other blocks.
+#[tauri::command]
+pub fn preview_block(
+    target: String,
+    patch: String,
+    retry: FuzzRetry,
+    accepted: Option<Vec<FuzzRetry>>,
+    relocations: Option<Vec<RelocationView>>,
+) -> Result<PreviewResult, String> {
+    preview_patch_impl(&target, &patch, &relocations.unwrap_or_default(), &accepted.unwrap_or_default(), Some(&retry))
+        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-pub fn apply_patch(target: String, patch: String) -> Result<String, String> {
-    apply_patch_impl(&target, &patch).map_err(|e| e.to_string())
+pub fn apply_patch(
+    target: String,
+    patch: String,
+    relocations: Option<Vec<RelocationView>>,
+    retries: Option<Vec<FuzzRetry>>,
+    allow_partial: Option<bool>,
+) -> Result<ApplyOutcome, String> {
+    apply_patch_impl(
+        &target,
+        &patch,
+        &relocations.unwrap_or_default(),
+        &retries.unwrap_or_default(),
+        allow_partial.unwrap_or(false),
+    )
+    .map_err(|e| e.to_string())
 }
 
 /* ========================== Impl ========================== */
 
-fn preview_patch_impl(target: &str, patch: &str) -> PatchResult<PreviewResult> {
+fn preview_patch_impl(
+    target: &str,
+    patch: &str,
+    relocations: &[RelocationView],
+    accepted: &[FuzzRetry],
+    retry: Option<&FuzzRetry>,
+) -> PatchResult<PreviewResult> {
     use applydiff_core::error::{ErrorCode, PatchError};
 
     let rid = generate_rid();
@@ -81,70 +160,72 @@ fn preview_patch_impl(target: &str, patch: &str) -> PatchResult<PreviewResult> {
     }
 
     let parser = Parser::new();
-    let blocks = parser.parse(patch)?;
+    let mut blocks = parser.parse(patch)?;
     log.push_str(&format!("✔ Parsed {} patch block(s)\n\n", blocks.len()));
+    // thresholds the user already confirmed, as apply_patch will use them
+    for accepted in accepted {
+        if let Some(block) = blocks.get_mut(accepted.block) {
+            block.fuzz = accepted.fuzz.clamp(0.0, 1.0);
+        }
+    }
 
-    let applier = Applier::new(&logger, target_path.clone(), true);
-    for (idx, block) in blocks.iter().enumerate() {
+    // the block a retry reports on; it runs with the rest of the patch
+    let retried = match retry {
+        Some(retry) if retry.block < blocks.len() => {
+            let block = &mut blocks[retry.block];
+            block.fuzz = retry.fuzz.clamp(0.0, 1.0);
+            log.push_str(&format!("↻ Retrying block {} with fuzz={:.2}\n\n", retry.block + 1, block.fuzz));
+            Some(retry.block)
+        }
+        Some(retry) => {
+            return Err(PatchError::Validation {
+                code: ErrorCode::ValidationFailed,
+                message: format!("Block {} does not exist; the patch has {}", retry.block + 1, blocks.len()),
+                context: "retry".to_string(),
+            })
+        }
+        None => None,
+    };
+
+    let config = ProjectConfig::load(&target_path)?;
+    // the same engine as apply_patch, never committed
+    let preview = run_by_file(&blocks, config.parallel, || {
+        Applier::new(&logger, target_path.clone(), true)
+            .with_config(config.clone())
+            .with_relocations(confirmed(relocations))
+    });
+    let mut retries = Vec::new();
+    let mut canonical = Vec::new();
+    let mut files: Vec<PathBuf> = Vec::new();
+    for (idx, ((block, result), miss)) in blocks.iter().zip(&preview.results).zip(&preview.near_misses).enumerate() {
+        if retried.is_some_and(|r| r != idx) {
+            continue;
+        }
         log.push_str(&format!("Block {}: {}\n", idx + 1, block.file.display()));
-        match applier.apply_block(block) {
+        if let Some(fuzz) = miss.as_ref().and_then(|m| m.suggested_fuzz()) {
+            retries.push(FuzzRetry { block: idx, fuzz });
+        }
+        match result {
             Ok(result) => {
                 log.push_str(&format!(
-                    "  ✔ Preview match at offset {} (score: {:.2})\n",
-                    result.matched_at, result.score
+                    "  ✔ Preview match at offset {} (tier: {}, score: {:.2}{})\n",
+                    result.matched_at, result.tier, result.score, format_margin(result.margin)
                 ));
-
-                let file_path = target_path.join(&block.file);
-                let content = fs::read_to_string(&file_path).unwrap_or_default();
-
-                let start = result.matched_at as usize;
-                let end = result.matched_end.min(content.len());
-
-                if start <= end {
-                    let before = &content[start..end];
-
-                    // Harmonize EOLs
-                    let matched_nl = if before.ends_with("\r\n") {
-                        "\r\n"
-                    } else if before.ends_with('\n') {
-                        "\n"
-                    } else {
-                        ""
-                    };
-                    
-                    let mut to_text = block.to.clone();
-                    if !matched_nl.is_empty() {
-                        if to_text.ends_with("\r\n") && matched_nl == "\n" {
-                            let new_len = to_text.len().saturating_sub(2);
-                            to_text.truncate(new_len);
-                            to_text.push('\n');
-                        } else if to_text.ends_with('\n') && matched_nl == "\r\n" {
-                            to_text.pop();
-                            to_text.push_str("\r\n");
-                        } else if !to_text.ends_with('\n') && !to_text.ends_with("\r\n") {
-                            to_text.push_str(matched_nl);
-                        }
-                    }
-
-                    // Mirror append separator newline from apply logic
-                    if start == content.len() && !content.is_empty() && !content.ends_with('\n') && !to_text.is_empty() {
-                        to_text.insert(0, '\n');
-                    }
-
-                    let udiff = TextDiff::from_lines(before, &to_text)
-                        .unified_diff()
-                        .header(
-                            &format!("a/{}", block.file.display()),
-                            &format!("b/{}", block.file.display()),
-                        )
-                        .to_string();
-
-                    if !udiff.trim().is_empty() {
-                        diffs.push_str(&udiff);
-                        if !diffs.ends_with('\n') {
-                            diffs.push('\n');
-                        }
-                    }
+                if result.occurrences > 1 {
+                    log.push_str(&format!("  ✔ Replaces {} occurrences\n", result.occurrences));
+                }
+                if let Some(assumed) = &result.assumed {
+                    log.push_str(&format_assumed(assumed));
+                }
+                for note in &result.notes {
+                    log.push_str(&format!("  ⚠ {}\n", note));
+                }
+                if !result.tolerated.is_empty() {
+                    log.push_str(&format_tolerated(&result.tolerated));
+                }
+                canonical.push(result.canonical.clone());
+                if !files.contains(&result.file) {
+                    files.push(result.file.clone());
                 }
             }
             Err(e) => {
@@ -153,11 +234,57 @@ fn preview_patch_impl(target: &str, patch: &str) -> PatchResult<PreviewResult> {
         }
     }
 
+    // one diff per file: the file on disk against its content after every block that matched
+    for file in &files {
+        let before = fs::read_to_string(target_path.join(file)).unwrap_or_default();
+        let after = preview.content(file).unwrap_or_default();
+        let udiff = TextDiff::from_lines(before.as_str(), after)
+            .unified_diff()
+            .header(&format!("a/{}", file.display()), &format!("b/{}", file.display()))
+            .to_string();
+        if !udiff.trim().is_empty() {
+            diffs.push_str(&udiff);
+            if !diffs.ends_with('\n') {
+                diffs.push('\n');
+            }
+        }
+    }
+
+    let relocations: Vec<RelocationView> = preview
+        .relocations
+        .into_iter()
+        .map(|r| RelocationView { named: r.named.display().to_string(), found: r.found.display().to_string() })
+        .collect();
+    if !relocations.is_empty() {
+        log.push_str(&format!(
+            "\n🔎 {} path(s) in the patch look wrong; the FROM text was found elsewhere:\n",
+            relocations.len()
+        ));
+        for r in &relocations {
+            log.push_str(&format!("  {} → {}\n", r.named, r.found));
+        }
+        log.push_str("  Confirm to preview and apply those blocks in the suggested files.\n");
+    }
+
+    if !retries.is_empty() {
+        log.push_str(&format!("\n🎯 {} block(s) would match at a lower fuzz threshold:\n", retries.len()));
+        for r in &retries {
+            log.push_str(&format!("  Block {} → fuzz={:.2}\n", r.block + 1, r.fuzz));
+        }
+        log.push_str("  Re-preview a block with its suggested threshold before applying.\n");
+    }
+
     log.push_str("\n💡 Preview complete. Press 'Apply Patch' to make changes.");
-    Ok(PreviewResult { log, diff: diffs })
+    Ok(PreviewResult { log, diff: diffs, relocations, retries, canonical: render_patch(&canonical) })
 }
 
-fn apply_patch_impl(target: &str, patch: &str) -> PatchResult<String> {
+fn apply_patch_impl(
+    target: &str,
+    patch: &str,
+    relocations: &[RelocationView],
+    retries: &[FuzzRetry],
+    allow_partial: bool,
+) -> PatchResult<ApplyOutcome> {
     use applydiff_core::error::{ErrorCode, PatchError};
 
     let rid = generate_rid();
@@ -183,28 +310,55 @@ fn apply_patch_impl(target: &str, patch: &str) -> PatchResult<String> {
     }
 
     let parser = Parser::new();
-    let blocks = parser.parse(patch)?;
+    let mut blocks = parser.parse(patch)?;
     output.push_str(&format!("✔ Parsed {} patch block(s)\n", blocks.len()));
-
-    // Backup before applying
-    let files_to_backup: Vec<PathBuf> = blocks.iter().map(|b| b.file.clone()).collect();
-    let backup_dir = backup::create_backup(&target_path, &files_to_backup)?;
-    output.push_str(&format!("✔ Backup created at {}\n", backup_dir.display()));
-
-    // Apply (partial success allowed)
-    let applier = Applier::new(&logger, target_path.clone(), false);
+    // thresholds the user confirmed after a retried preview
+    for retry in retries {
+        if let Some(block) = blocks.get_mut(retry.block) {
+            block.fuzz = retry.fuzz.clamp(0.0, 1.0);
+            output.push_str(&format!("✔ Block {} uses fuzz={:.2} (confirmed)\n", retry.block + 1, block.fuzz));
+        }
+    }
+    let config = ProjectConfig::load(&target_path)?;
+
+    // The preview's engine, committed: all-or-nothing unless the user allowed partial success
+    let mut run = run_by_file(&blocks, config.parallel, || {
+        Applier::new(&logger, target_path.clone(), false)
+            .with_config(config.clone())
+            .with_relocations(confirmed(relocations))
+    });
+    // Back up exactly the files the commit will replace, and only when it is about to write
+    let pending = run.pending(allow_partial);
+    let backed_up = !pending.is_empty();
+    if backed_up {
+        let backup_dir = backup::create_backup(&target_path, &pending)?;
+        output.push_str(&format!("✔ Backup created at {}\n", backup_dir.display()));
+    }
+    let committed = run.commit(allow_partial);
+    let verb = if matches!(committed, Some(Ok(_))) { "Applied" } else { "Matched" };
     let mut success = 0usize;
     let mut failed = 0usize;
+    let mut canonical = Vec::new();
 
-    for (idx, block) in blocks.iter().enumerate() {
+    for ((idx, block), result) in blocks.iter().enumerate().zip(run.results) {
         output.push_str(&format!("Block {}: {}\n", idx + 1, block.file.display()));
-        match applier.apply_block(block) {
+        match result {
             Ok(result) => {
                 success += 1;
                 output.push_str(&format!(
-                    "  ✔ Applied at offset {} (score: {:.2})\n",
-                    result.matched_at, result.score
+                    "  ✔ {} at offset {} (tier: {}, score: {:.2}{})\n",
+                    verb, result.matched_at, result.tier, result.score, format_margin(result.margin)
                 ));
+                if result.occurrences > 1 {
+                    output.push_str(&format!("  ✔ Replaced {} occurrences\n", result.occurrences));
+                }
+                if let Some(assumed) = &result.assumed {
+                    output.push_str(&format_assumed(assumed));
+                }
+                for note in &result.notes {
+                    output.push_str(&format!("  ⚠ {}\n", note));
+                }
+                canonical.push(result.canonical);
             }
             Err(e) => {
                 failed += 1;
@@ -213,9 +367,90 @@ fn apply_patch_impl(target: &str, patch: &str) -> PatchResult<String> {
         }
     }
 
-    output.push_str(&format!("\n✅ Done. {} applied, {} failed.\n", success, failed));
-    output.push_str("↩ Backups live next to your files in a timestamped .applydiff_backup_* folder.\n");
-    Ok(output)
+    match committed {
+        None => {
+            output.push_str(&format!(
+                "\n✖ Nothing written: {} of {} block(s) failed. Fix them, or allow a partial apply to write the rest.\n",
+                failed,
+                blocks.len()
+            ));
+            return Ok(ApplyOutcome { status: ApplyStatus::NothingWritten, log: output });
+        }
+        Some(Ok(files)) => output.push_str(&format!(
+            "\n✅ Done. {} applied, {} failed; {} file(s) written.\n",
+            success,
+            failed,
+            files.len()
+        )),
+        Some(Err(e)) => {
+            output.push_str(&format!("\n❌ Write failed: {}\n", e));
+            if backed_up {
+                output.push_str("↩ Backups live next to your files in a timestamped .applydiff_backup_* folder.\n");
+            }
+            return Ok(ApplyOutcome { status: ApplyStatus::Failed, log: output });
+        }
+    }
+    if !canonical.is_empty() {
+        output.push_str("\n📄 Canonical patch of the applied blocks (re-applies without fuzz):\n");
+        output.push_str(&render_patch(&canonical));
+        output.push('\n');
+    }
+    if backed_up {
+        output.push_str("↩ Backups live next to your files in a timestamped .applydiff_backup_* folder.\n");
+    }
+    Ok(ApplyOutcome { status: ApplyStatus::Written, log: output })
+}
+
+/// Relocations the user accepted in the preview
+fn confirmed(relocations: &[RelocationView]) -> Vec<Relocation> {
+    relocations
+        .iter()
+        .map(|r| Relocation { named: PathBuf::from(&r.named), found: PathBuf::from(&r.found) })
+        .collect()
+}
+
+fn format_margin(margin: Option<f64>) -> String {
+    margin.map(|m| format!(", margin: {:.2}", m)).unwrap_or_default()
+}
+
+/// Highlight the lines an anchor-sandwich match took on trust
+fn format_assumed(assumed: &AssumedSpan) -> String {
+    let mut out = if assumed.last_line >= assumed.first_line {
+        format!(
+            "  ⚠ Matched by anchors; lines {}-{} differ from the patch and were assumed:\n",
+            assumed.first_line, assumed.last_line
+        )
+    } else {
+        format!("  ⚠ Matched by anchors; the patch has extra lines before line {}:\n", assumed.first_line)
+    };
+    for line in assumed.file_text.lines() {
+        out.push_str(&format!("      file  │ {}\n", line));
+    }
+    for line in assumed.patch_text.lines() {
+        out.push_str(&format!("      patch │ {}\n", line));
+    }
+    out
+}
+
+/// Show what a non-exact match tolerated, git word-diff style: `[-patch-]{+file+}`
+fn format_tolerated(tolerated: &[ToleratedLine]) -> String {
+    let mut out = format!(
+        "  ≈ Match tolerated {} differing line(s) ([-patch-]{{+file+}}):\n",
+        tolerated.len()
+    );
+    for line in tolerated {
+        let at = line.file_line.map(|n| format!("line {}", n)).unwrap_or_else(|| "patch".to_string());
+        let mut text = String::new();
+        for (tag, run) in &line.segments {
+            match tag {
+                ChangeTag::Equal => text.push_str(run),
+                ChangeTag::Delete => text.push_str(&format!("[-{}-]", run)),
+                ChangeTag::Insert => text.push_str(&format!("{{+{}+}}", run)),
+            }
+        }
+        out.push_str(&format!("      {:>9} │ {}\n", at, text));
+    }
+    out
 }
 
 fn generate_rid() -> u64 {
@@ -11,6 +11,7 @@ fn main() {
             commands::pick_folder,
             commands::get_ai_prompt,
             commands::preview_patch,
+            commands::preview_block,
             commands::apply_patch,
             commands::run_self_test,
         ])
@@ -13,7 +13,10 @@
     versions: [],
     currentVersion: -1,
     consoleVisible: false,
-    previewInFlight: false
+    previewInFlight: false,
+    relocations: [],
+    retries: [],
+    canonicalPatch: ''
   };
 
   // Event helper: emit custom event
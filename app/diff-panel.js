@@ -28,8 +28,10 @@
         return;
       }
       
-      logToConsole('🔧 Apply button clicked');
-      window.emitAppEvent('apply-requested', { patch, diff });
+      // "Apply Valid Changes" is the explicit opt-in to a partial apply; otherwise all-or-nothing
+      const allowPartial = applyBtn.classList.contains('warn');
+      logToConsole('🔧 Apply button clicked' + (allowPartial ? ' (partial apply allowed)' : ''));
+      window.emitAppEvent('apply-requested', { patch, diff, allowPartial });
     });
   }
 
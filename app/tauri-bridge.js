@@ -42,16 +42,22 @@
   // Preview patch
   window.onAppEvent('preview-requested', async (e) => {
     const { patch } = e.detail;
+    const relocations = e.detail.relocations || [];
     const dir = window.AppState.selectedDir;
     
     if (!dir || !patch) return;
     if (window.AppState.previewInFlight) return;
     
     window.AppState.previewInFlight = true;
+    window.AppState.relocations = relocations;
+    window.AppState.retries = [];
+    window.AppState.canonicalPatch = '';
     window.setStatus('previewing…', 'warn');
     
+    let confirmedRelocations = null;
+    let retries = [];
     try {
-      const res = await invoke('preview_patch', { target: dir, patch });
+      const res = await invoke('preview_patch', { target: dir, patch, relocations });
       
       if (res && res.log) {
         const tail = res.log.split('\n').slice(-40).join('\n');
@@ -67,6 +73,23 @@
         hasDiff
       });
       
+      // Wrong paths found elsewhere: ask once, then preview again with the accepted relocations
+      const suggested = res?.relocations || [];
+      if (suggested.length && !relocations.length) {
+        const list = suggested.map(r => '  ' + r.named + ' → ' + r.found).join('\n');
+        if (window.confirm('These blocks were not found in the files they name:\n\n' + list + '\n\nApply them to the suggested files instead?')) {
+          confirmedRelocations = suggested;
+        }
+      }
+
+      retries = res?.retries || [];
+
+      // The matched blocks with their exact file text as FROM, for saving or re-applying elsewhere
+      window.AppState.canonicalPatch = res?.canonical || '';
+      if (window.AppState.canonicalPatch) {
+        logToConsole('📄 Canonical patch of the matched blocks:\n' + window.AppState.canonicalPatch);
+      }
+
       if (!hasDiff) {
         window.setStatus('idle');
       } else if (hasError) {
@@ -81,11 +104,37 @@
     } finally {
       window.AppState.previewInFlight = false;
     }
+    if (confirmedRelocations) {
+      window.emitAppEvent('preview-requested', { patch, relocations: confirmedRelocations });
+    } else {
+      await offerRetries(dir, patch, relocations, retries);
+    }
   });
 
+  // Blocks that narrowly missed: re-preview each one the user accepts at its suggested
+  // threshold, and keep the thresholds that matched for the next apply
+  async function offerRetries(dir, patch, relocations, retries) {
+    for (const retry of retries) {
+      const question = 'Block ' + (retry.block + 1) + ' did not match, but would at fuzz=' +
+        retry.fuzz.toFixed(2) + '.\n\nPreview it again with that threshold?';
+      if (!window.confirm(question)) continue;
+      try {
+        const accepted = window.AppState.retries || [];
+        const res = await invoke('preview_block', { target: dir, patch, retry, accepted, relocations });
+        logToConsole('↻ Retry preview:\n' + (res?.log || '') + '\n' + (res?.diff || ''));
+        if (!/❌/.test(res?.log || '')) {
+          window.AppState.retries.push(retry);
+        }
+      } catch (e) {
+        logToConsole('❌ Retry preview error: ' + e, 'error');
+      }
+    }
+  }
+
   // Apply patch
   window.onAppEvent('apply-requested', async (e) => {
     const { patch, diff } = e.detail;
+    const allowPartial = !!e.detail.allowPartial;
     const dir = window.AppState.selectedDir;
     
     if (!dir || !patch) return;
@@ -93,11 +142,23 @@
     window.setStatus('applying…', 'warn');
     
     try {
-      const out = await invoke('apply_patch', { target: dir, patch });
-      logToConsole('✅ Apply:\n' + out);
+      const relocations = window.AppState.relocations || [];
+      const retries = window.AppState.retries || [];
+      const { status, log } = await invoke('apply_patch', { target: dir, patch, relocations, retries, allowPartial });
+      if (status === 'nothing_written') {
+        logToConsole('⚠️ Apply:\n' + log, 'error');
+        window.setStatus('nothing applied', 'err');
+        return;
+      }
+      if (status === 'failed') {
+        logToConsole('❌ Apply:\n' + log, 'error');
+        window.setStatus('write failed', 'err');
+        return;
+      }
+      logToConsole('✅ Apply:\n' + log);
       window.setStatus('applied', 'ok');
       
-      window.emitAppEvent('apply-complete', { patch, diff, log: out });
+      window.emitAppEvent('apply-complete', { patch, diff, log });
     } catch (e) {
       logToConsole('❌ Apply failed: ' + e, 'error');
       window.setStatus('apply failed', 'err');
@@ -0,0 +1,308 @@
+use crate::r#match::normalize_ws_preserve_newlines;
+use similar::{capture_diff_slices, Algorithm, DiffOp};
+use strsim::normalized_damerau_levenshtein;
+
+/// Least similarity at which a `from` line that differs from every file line is
+/// still taken for the file line it was meant to be (a typo, a renamed token)
+const PAIR_MIN_SIMILARITY: f64 = 0.6;
+
+/// Replay the `from` → `to` edit onto the file region an aligned match found.
+/// Context lines keep the file's bytes, and lines only the file has (the ones
+/// `from` dropped) stay where they were instead of being deleted with the
+/// region.
+pub fn merge_aligned(to: &str, from: &str, matched: &str) -> Merged {
+    merge_aligned_keyed(to, from, matched, |text| text.lines().map(normalize_ws_preserve_newlines).collect())
+}
+
+/// The replayed edit
+pub struct Merged {
+    pub text: String,
+    /// File-only lines kept in place
+    pub kept: usize,
+    /// Lines `to` removes or rewrites that matched no file line; the file-only
+    /// lines kept may then be their stale originals
+    pub unplaced: usize,
+}
+
+/// `merge_aligned` with a caller-chosen line comparison: `keys` maps a text to
+/// one key per line (e.g. code with comments stripped). Lines with an empty key
+/// (blank, comment-only) take no part in the alignment; each run of them travels
+/// with the line after it, and where `from` and `to` disagree on a run, `to`'s wins.
+pub fn merge_aligned_keyed(
+    to: &str,
+    from: &str,
+    matched: &str,
+    keys: impl Fn(&str) -> Vec<String>,
+) -> Merged {
+    let capacity = matched.len() + to.len();
+    let file = Split::new(matched.split_inclusive('\n').collect(), keys(matched));
+    let from = Split::new(from.lines().collect(), keys(from));
+    let to = Split::new(to.lines().collect(), keys(to));
+    let nl = if matched.contains("\r\n") { "\r\n" } else { "\n" };
+
+    // from-line index -> file line with the same normalized content, or failing
+    // that the most similar one in the same unequal stretch
+    let mut in_file: Vec<Option<usize>> = vec![None; from.keys.len()];
+    for op in capture_diff_slices(Algorithm::Patience, &from.keys, &file.keys) {
+        match op {
+            DiffOp::Equal { old_index, new_index, len } => {
+                for k in 0..len {
+                    in_file[old_index + k] = Some(new_index + k);
+                }
+            }
+            DiffOp::Replace { old_index, old_len, new_index, new_len } => {
+                let (from_range, file_range) = (old_index..old_index + old_len, new_index..new_index + new_len);
+                pair_similar(&from.keys, from_range, &file.keys, file_range, &mut in_file);
+            }
+            _ => {}
+        }
+    }
+
+    let mut out = String::with_capacity(capacity);
+    let mut next_file = 0usize;
+    let mut kept = 0usize;
+    let mut unplaced = 0usize;
+    // Emit file-only lines (with their runs) up to file line `f`, then the run
+    // before `f` as the edit leaves it: `from` line `i` took it and `to_run` replaces it
+    let mut reach = |out: &mut String, f: usize, taken: Option<(usize, &[&str])>| {
+        for k in next_file.min(f)..f {
+            push_lines(out, &file.runs[k], nl);
+            push_line(out, file.lines[k], nl);
+            kept += file.runs[k].len() + 1;
+        }
+        next_file = next_file.max(f + 1);
+        if let Some((i, to_run)) = taken {
+            if !same_run(&from.runs[i], to_run) {
+                push_lines(out, to_run, nl);
+            } else {
+                if !same_run(&file.runs[f], &from.runs[i]) {
+                    kept += file.runs[f].len();
+                }
+                push_lines(out, &file.runs[f], nl);
+            }
+        }
+    };
+
+    for op in capture_diff_slices(Algorithm::Myers, &from.keys, &to.keys) {
+        match op {
+            DiffOp::Equal { old_index, new_index, len } => {
+                for k in 0..len {
+                    let (i, j) = (old_index + k, new_index + k);
+                    match in_file[i] {
+                        Some(f) => {
+                            reach(&mut out, f, Some((i, &to.runs[j])));
+                            push_line(&mut out, file.lines[f], nl);
+                        }
+                        // a context line the file doesn't have is dropped, not invented
+                        None if !same_run(&from.runs[i], &to.runs[j]) => push_lines(&mut out, &to.runs[j], nl),
+                        None => {}
+                    }
+                }
+            }
+            DiffOp::Delete { old_index, old_len, .. } => {
+                for (i, taken) in in_file.iter().enumerate().skip(old_index).take(old_len) {
+                    match *taken {
+                        Some(f) => reach(&mut out, f, Some((i, &[]))),
+                        None => unplaced += 1,
+                    }
+                }
+            }
+            DiffOp::Insert { new_index, new_len, .. } => {
+                for j in new_index..new_index + new_len {
+                    push_lines(&mut out, &to.runs[j], nl);
+                    push_line(&mut out, to.lines[j], nl);
+                }
+            }
+            DiffOp::Replace { old_index, old_len, new_index, new_len } => {
+                // the run before the first replaced line is where `to`'s first run goes
+                let mut first_run = Some(&to.runs[new_index]);
+                for (i, taken) in in_file.iter().enumerate().skip(old_index).take(old_len) {
+                    match *taken {
+                        Some(f) => {
+                            let to_run = first_run.take().map_or(&[][..], |run| &run[..]);
+                            reach(&mut out, f, Some((i, to_run)));
+                        }
+                        None => unplaced += 1,
+                    }
+                }
+                for j in new_index..new_index + new_len {
+                    if j > new_index || first_run.is_some() {
+                        push_lines(&mut out, &to.runs[j], nl);
+                    }
+                    push_line(&mut out, to.lines[j], nl);
+                }
+            }
+        }
+    }
+    reach(&mut out, file.lines.len(), None);
+    let (file_tail, from_tail, to_tail) = (file.tail(), from.tail(), to.tail());
+    if !same_run(from_tail, to_tail) {
+        push_lines(&mut out, to_tail, nl);
+    } else {
+        if !same_run(file_tail, from_tail) {
+            kept += file_tail.len();
+        }
+        push_lines(&mut out, file_tail, nl);
+    }
+
+    // the caller harmonizes the trailing EOL with the matched slice
+    if out.ends_with(nl) {
+        out.truncate(out.len() - nl.len());
+    }
+    Merged { text: out, kept, unplaced }
+}
+
+/// A text's lines with a non-empty key, each with the run of empty-key lines
+/// before it; `runs` has one more entry, the run after the last line
+struct Split<'t> {
+    lines: Vec<&'t str>,
+    keys: Vec<String>,
+    runs: Vec<Vec<&'t str>>,
+}
+
+impl<'t> Split<'t> {
+    fn new(all: Vec<&'t str>, all_keys: Vec<String>) -> Self {
+        let mut split = Split { lines: Vec::new(), keys: Vec::new(), runs: Vec::new() };
+        let mut run = Vec::new();
+        for (line, key) in all.into_iter().zip(all_keys) {
+            if key.trim().is_empty() {
+                run.push(line);
+            } else {
+                split.lines.push(line);
+                split.keys.push(key);
+                split.runs.push(std::mem::take(&mut run));
+            }
+        }
+        split.runs.push(run);
+        split
+    }
+
+    fn tail(&self) -> &[&'t str] {
+        &self.runs[self.lines.len()]
+    }
+}
+
+/// Runs hold the same lines, whitespace aside
+fn same_run<S: AsRef<str>>(a: &[S], b: &[&str]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).all(|(x, y)| x.as_ref().split_whitespace().eq(y.split_whitespace()))
+}
+
+/// Pair each `from` line in `from_range` with its most similar file line in
+/// `file_range`, in order, skipping pairs under `PAIR_MIN_SIMILARITY`
+fn pair_similar(
+    from_keys: &[String],
+    from_range: std::ops::Range<usize>,
+    file_keys: &[String],
+    file_range: std::ops::Range<usize>,
+    in_file: &mut [Option<usize>],
+) {
+    let mut next = file_range.start;
+    for i in from_range {
+        let best = (next..file_range.end)
+            .map(|f| (f, normalized_damerau_levenshtein(&from_keys[i], &file_keys[f])))
+            .filter(|&(_, sim)| sim >= PAIR_MIN_SIMILARITY)
+            .max_by(|a, b| a.1.total_cmp(&b.1).then(b.0.cmp(&a.0)));
+        if let Some((f, _)) = best {
+            in_file[i] = Some(f);
+            next = f + 1;
+        }
+    }
+}
+
+fn push_lines<S: AsRef<str>>(out: &mut String, lines: &[S], nl: &str) {
+    for line in lines {
+        push_line(out, line.as_ref(), nl);
+    }
+}
+
+fn push_line(out: &mut String, line: &str, nl: &str) {
+    out.push_str(line.trim_end_matches(['\r', '\n']));
+    out.push_str(nl);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{merge_aligned, merge_aligned_keyed};
+    use crate::apply::Applier;
+    use crate::error::{ErrorCode, PatchError};
+    use crate::logger::Logger;
+    use crate::parse::Parser;
+    use crate::r#match::{code_lines, comment_syntax, FuzzyStrategy, MatchPipeline, PatienceAlignStrategy};
+    use crate::test_helpers::{cleanup, make_sandbox};
+    use std::fs;
+
+    #[test]
+    fn keeps_lines_the_block_dropped() {
+        let matched = "a = 1\n\n# tuning\nb = 2\nc = 3\n";
+        let from = "a = 1\nb = 2\nc = 3";
+        let to = "a = 1\nb = 20\nc = 3";
+        let merged = merge_aligned(to, from, matched);
+        assert_eq!(merged.text, "a = 1\n\n# tuning\nb = 20\nc = 3");
+        assert_eq!((merged.kept, merged.unplaced), (2, 0));
+    }
+
+    #[test]
+    fn pairs_lines_that_carry_a_typo() {
+        let matched = "def load(path):\n    data = read(path)\n    return parse(data)\n";
+        let from = "def lod(path):\n    data = reed(path)\n    return parse(data)";
+        let to = "def lod(path):\n    data = read_text(path)\n    return parse(data)";
+        let merged = merge_aligned(to, from, matched);
+        assert_eq!(merged.text, "def load(path):\n    data = read_text(path)\n    return parse(data)");
+        assert_eq!((merged.kept, merged.unplaced), (0, 0));
+    }
+
+    #[test]
+    fn comment_lines_follow_their_code_and_take_to_s_version() {
+        let syntax = comment_syntax("lib.rs".as_ref()).unwrap();
+        let merge = |to, from, matched| merge_aligned_keyed(to, from, matched, |t| code_lines(t, syntax));
+
+        let file = "// old note\nfoo(2);\n// trailing info\nbar();\n";
+        let merged = merge("// new note\nfoo(3);\nbar();", "// old note\nfoo(2);\nbar();", file);
+        assert_eq!(merged.text, "// new note\nfoo(3);\n// trailing info\nbar();");
+        assert_eq!(merged.kept, 1);
+
+        // `from` left the comment out and `to` rewrites it
+        let file = "setup();\n// retry twice\nretry(2);\n";
+        let merged = merge("setup();\n// retry thrice\nretry(3);", "setup();\nretry(2);", file);
+        assert_eq!(merged.text, "setup();\n// retry thrice\nretry(3);");
+    }
+
+    #[test]
+    fn counts_changed_lines_with_no_file_line() {
+        let matched = "app.debug = False\n# keep in sync\nRETRIES = 3\n";
+        let from = "app.debug = False\nretry_count(3)";
+        let to = "app.debug = False\nretry_count(5)";
+        let merged = merge_aligned(to, from, matched);
+        assert_eq!((merged.kept, merged.unplaced), (2, 1));
+    }
+
+    #[test]
+    fn widened_fuzzy_window_replaces_the_line_with_a_typo() {
+        let root = make_sandbox().unwrap();
+        fs::write(root.join("cfg.py"), "app.debug = False\n# keep in sync\napp.retries = 3\napp.name = 'svc'\n").unwrap();
+        let logger = Logger::new_for_test(1, None);
+        let patch = ">>> file: cfg.py | fuzz=0.6\n--- from\napp.debug = False\napp.retrys = 3\n--- to\napp.debug = False\napp.retries = 5\n<<<\n";
+        let applier = Applier::new(&logger, root.clone(), false).with_pipeline(MatchPipeline::empty().with(FuzzyStrategy));
+        applier.apply_block(&Parser::new().parse(patch).unwrap()[0]).unwrap();
+        assert_eq!(
+            fs::read_to_string(root.join("cfg.py")).unwrap(),
+            "app.debug = False\n# keep in sync\napp.retries = 5\napp.name = 'svc'\n"
+        );
+        cleanup(&root).ok();
+    }
+
+    #[test]
+    fn aligned_match_refuses_a_changed_line_with_no_file_line() {
+        let root = make_sandbox().unwrap();
+        let file = "def run():\n    setup()\n    # warm the cache\n    load_cache()\n    retries = 3\n    serve()\n    teardown()\n";
+        fs::write(root.join("app.py"), file).unwrap();
+        let logger = Logger::new_for_test(1, None);
+        let patch = ">>> file: app.py | fuzz=0.6\n--- from\ndef run():\n    setup()\n    load_cache()\n    attempts_total = compute()\n    serve()\n    teardown()\n\
+                     --- to\ndef run():\n    setup()\n    load_cache()\n    attempts_total = compute(5)\n    serve()\n    teardown()\n<<<\n";
+        let applier = Applier::new(&logger, root.clone(), false).with_pipeline(MatchPipeline::empty().with(PatienceAlignStrategy));
+        let refused = applier.apply_block(&Parser::new().parse(patch).unwrap()[0]);
+        assert!(matches!(refused, Err(PatchError::Apply { code: ErrorCode::TierRejected, .. })), "{:?}", refused.err());
+        assert_eq!(fs::read_to_string(root.join("app.py")).unwrap(), file);
+        cleanup(&root).ok();
+    }
+}
@@ -0,0 +1,125 @@
+use crate::r#match::AssumedSpan;
+use similar::{capture_diff_slices, Algorithm, DiffOp};
+
+/// After an anchor-sandwich match, swap the model's version of the assumed middle
+/// back to the file's lines when `to` carries that middle over unchanged, so
+/// only the edits at the anchors land. Returns `None` when `to` edits the middle
+/// itself; the caller then refuses the block rather than overwrite lines the patch never held.
+pub fn keep_assumed_middle(to: &str, from: &str, assumed: &AssumedSpan) -> Option<String> {
+    let from_lines: Vec<&str> = from.lines().map(|l| l.trim_end_matches('\r')).collect();
+    let to_lines: Vec<&str> = to.lines().map(|l| l.trim_end_matches('\r')).collect();
+
+    // from-line index -> to-line index for lines `to` kept verbatim
+    let mut kept: Vec<Option<usize>> = vec![None; from_lines.len()];
+    for op in capture_diff_slices(Algorithm::Myers, &from_lines, &to_lines) {
+        if let DiffOp::Equal { old_index, new_index, len } = op {
+            for k in 0..len {
+                kept[old_index + k] = Some(new_index + k);
+            }
+        }
+    }
+
+    // The middle must survive as one contiguous run; an empty middle needs a kept neighbour
+    let first = assumed.patch_first_line.checked_sub(1)?;
+    let count = assumed.patch_text.lines().count();
+    let at = if count == 0 {
+        match (first.checked_sub(1).and_then(|i| kept.get(i).copied().flatten()), kept.get(first).copied().flatten()) {
+            (Some(j), _) => j + 1,
+            (None, Some(j)) => j,
+            (None, None) => return None,
+        }
+    } else {
+        let at = (*kept.get(first)?)?;
+        if (0..count).any(|k| kept.get(first + k).copied().flatten() != Some(at + k)) {
+            return None;
+        }
+        at
+    };
+
+    let mut out = String::with_capacity(to.len() + assumed.file_text.len());
+    let mut inserted = false;
+    for (idx, line) in to.split_inclusive('\n').enumerate() {
+        if idx == at {
+            out.push_str(&assumed.file_text);
+            inserted = true;
+        }
+        if idx < at || idx >= at + count {
+            out.push_str(line);
+        }
+    }
+    if !inserted {
+        if !out.is_empty() && !out.ends_with('\n') {
+            out.push('\n');
+        }
+        out.push_str(assumed.file_text.trim_end_matches(['\r', '\n']));
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::keep_assumed_middle;
+    use crate::apply::Applier;
+    use crate::error::{ErrorCode, PatchError};
+    use crate::logger::Logger;
+    use crate::parse::Parser;
+    use crate::r#match::{AssumedSpan, MatchTier};
+    use crate::test_helpers::{cleanup, make_sandbox};
+    use std::fs;
+
+    fn span(patch_first_line: usize, file_text: &str, patch_text: &str) -> AssumedSpan {
+        AssumedSpan {
+            first_line: 0,
+            last_line: 0,
+            patch_first_line,
+            file_text: file_text.to_string(),
+            patch_text: patch_text.to_string(),
+        }
+    }
+
+    #[test]
+    fn restores_file_middle_around_anchor_edits() {
+        let from = "fn a() {\n    guessed();\n}";
+        let to = "fn a() -> u8 {\n    guessed();\n    0\n}";
+        let assumed = span(2, "    real_one();\n    real_two();\n", "    guessed();");
+        assert_eq!(
+            keep_assumed_middle(to, from, &assumed).as_deref(),
+            Some("fn a() -> u8 {\n    real_one();\n    real_two();\n    0\n}")
+        );
+    }
+
+    #[test]
+    fn no_merge_when_middle_is_edited() {
+        let from = "head\n    guessed();\ntail";
+        let to = "head\n    changed();\ntail";
+        assert_eq!(keep_assumed_middle(to, from, &span(2, "    real();\n", "    guessed();")), None);
+    }
+
+    #[test]
+    fn paraphrased_middle_matches_at_default_fuzz_but_is_never_overwritten() {
+        let root = make_sandbox().unwrap();
+        let file = "def configure(app):\n    app.debug = False\n    # keep in sync with docs\n    app.retries = 3\n    app.timeout = 30\n    app.name = 'svc'\n    return register(app)\n";
+        fs::write(root.join("cfg.py"), file).unwrap();
+        let logger = Logger::new_for_test(1, None);
+        // FROM paraphrases the settings between the unique first and last lines
+        let block = |retries: &str, ret: &str| {
+            let patch = format!(
+                ">>> file: cfg.py\n--- from\ndef configure(app):\n    app.name = 'svc'\n    app.timeout = 30\n    app.debug = False\n    app.retries = 3\n    return register(app)\n\
+                 --- to\ndef configure(app):\n    app.name = 'svc'\n    app.timeout = 30\n    app.debug = False\n    app.retries = {}\n    {}\n<<<\n",
+                retries, ret
+            );
+            Parser::new().parse(&patch).unwrap().remove(0)
+        };
+        let applier = Applier::new(&logger, root.clone(), false);
+
+        // `to` rewrites a line of the middle the patch never held: refused, file untouched
+        let refused = applier.apply_block(&block("5", "return register(app)"));
+        assert!(matches!(refused, Err(PatchError::Apply { code: ErrorCode::TierRejected, .. })), "{:?}", refused.err());
+        assert_eq!(fs::read_to_string(root.join("cfg.py")).unwrap(), file);
+        // `to` only edits an anchor: applied, and the file's middle stays as it is
+        let applied = applier.apply_block(&block("3", "return register(app, frozen=True)")).unwrap();
+        assert_eq!(applied.tier, MatchTier::AnchorSandwich);
+        assert_eq!(fs::read_to_string(root.join("cfg.py")).unwrap(), file.replace("register(app)", "register(app, frozen=True)"));
+        cleanup(&root).ok();
+    }
+}
@@ -0,0 +1,239 @@
+use super::apply_transaction::{commit, StagedFile};
+use super::{file_key, Applier, ApplyResult};
+use crate::error::Result;
+use crate::locate::Relocation;
+use crate::logger::Logger;
+use crate::parse::PatchBlock;
+use crate::r#match::NearMiss;
+use rayon::prelude::*;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::{Arc, OnceLock};
+
+/// A patch run through the file-grouped engine. Files are only changed in memory
+/// until `commit`; a preview is a run that is never committed.
+pub struct PatchRun<'a> {
+    /// One result per block, in patch order
+    pub results: Vec<Result<ApplyResult>>,
+    /// Best rejected fuzzy candidate of each block that matched nothing, in patch order
+    pub near_misses: Vec<Option<NearMiss>>,
+    /// Relocations suggested across all files
+    pub relocations: Vec<Relocation>,
+    logger: &'a Logger,
+    root: PathBuf,
+    dry_run: bool,
+    /// Final content of every file the blocks read, by full path
+    staged: BTreeMap<PathBuf, StagedFile>,
+}
+
+/// Run `blocks` with one applier per file, files concurrently when `parallel` is set.
+/// Each file is read once and its blocks run in patch order against the evolving
+/// buffer, so a block sees the edits of the blocks before it and may not overwrite them.
+/// Blocks naming one file differently (`./a.txt`, `a.txt`) share its group.
+pub fn run_by_file<'a, F>(blocks: &[PatchBlock], parallel: bool, make_applier: F) -> PatchRun<'a>
+where
+    F: Fn() -> Applier<'a> + Sync,
+{
+    let probe = make_applier();
+    let mut groups: Vec<(PathBuf, Vec<usize>)> = Vec::new();
+    for (idx, blk) in blocks.iter().enumerate() {
+        let file = file_key(&probe.root, probe.target_of(blk));
+        match groups.iter_mut().find(|(target, _)| *target == file) {
+            Some((_, idxs)) => idxs.push(idx),
+            None => groups.push((file, vec![idx])),
+        }
+    }
+
+    // one project walk serves every file's near-miss path checks
+    let listing = Arc::new(OnceLock::new());
+    let run_group = |(_, idxs): &(PathBuf, Vec<usize>)| {
+        let applier = make_applier().staging().sharing_listing(&listing);
+        let results: Vec<(usize, Result<ApplyResult>, Option<NearMiss>)> = idxs
+            .iter()
+            .map(|&idx| {
+                let result = applier.apply_block(&blocks[idx]);
+                (idx, result, applier.last_near_miss())
+            })
+            .collect();
+        (results, applier.suggested_relocations(), applier.staged.take())
+    };
+    let per_file: Vec<_> = if parallel {
+        groups.par_iter().map(run_group).collect()
+    } else {
+        groups.iter().map(run_group).collect()
+    };
+
+    let mut results: Vec<Option<Result<ApplyResult>>> = blocks.iter().map(|_| None).collect();
+    let mut near_misses: Vec<Option<NearMiss>> = vec![None; blocks.len()];
+    let mut relocations: Vec<Relocation> = Vec::new();
+    let mut staged = BTreeMap::new();
+    for (file_results, file_suggested, file_staged) in per_file {
+        for (idx, result, miss) in file_results {
+            results[idx] = Some(result);
+            near_misses[idx] = miss;
+        }
+        for relocation in file_suggested {
+            if !relocations.contains(&relocation) {
+                relocations.push(relocation);
+            }
+        }
+        staged.extend(file_staged);
+    }
+    let results = results.into_iter().map(|r| r.expect("every block belongs to one file group")).collect();
+    PatchRun { results, near_misses, relocations, logger: probe.logger, root: probe.root.clone(), dry_run: probe.dry_run, staged }
+}
+
+impl PatchRun<'_> {
+    /// Final content of `file` (relative to the root) after the blocks that matched,
+    /// `None` if no block read it
+    pub fn content(&self, file: &std::path::Path) -> Option<&str> {
+        self.staged.get(&file_key(&self.root, file)).map(|f| f.content.as_str())
+    }
+
+    /// Files `commit(allow_partial)` is about to write (relative to the root): none when
+    /// the run is a dry run or would be refused, else every file whose content changed.
+    /// These are the files to back up before committing.
+    pub fn pending(&self, allow_partial: bool) -> Vec<PathBuf> {
+        if self.dry_run || (!allow_partial && self.results.iter().any(|r| r.is_err())) {
+            return Vec::new();
+        }
+        self.staged.iter().filter(|(_, file)| is_changed(file)).map(|(path, _)| self.relative(path)).collect()
+    }
+
+    /// Write the files the blocks changed, each through a temp file and rename, restoring
+    /// the ones already replaced if a later rename fails. Unless `allow_partial`, nothing
+    /// is written when any block failed. Returns the files written (relative to the root),
+    /// or `None` when the run was a dry run or was refused.
+    pub fn commit(&mut self, allow_partial: bool) -> Option<Result<Vec<PathBuf>>> {
+        let failed = self.results.iter().filter(|r| r.is_err()).count();
+        if failed > 0 && !allow_partial {
+            self.logger.info(
+                "applier",
+                "transaction_aborted",
+                &format!("{} of {} block(s) failed; no file written", failed, self.results.len()),
+            );
+            return None;
+        }
+        if self.dry_run {
+            return None;
+        }
+
+        let mut changed = std::mem::take(&mut self.staged);
+        changed.retain(|_, file| is_changed(file));
+        let committed = commit(&changed).map(|()| changed.keys().map(|path| self.relative(path)).collect::<Vec<_>>());
+        match &committed {
+            Ok(files) => self.logger.info("applier", "transaction_committed", &format!("{} file(s) written", files.len())),
+            Err(e) => self.logger.info("applier", "transaction_rolled_back", &e.to_string()),
+        }
+        Some(committed)
+    }
+
+    fn relative(&self, path: &std::path::Path) -> PathBuf {
+        path.strip_prefix(&self.root).unwrap_or(path).to_path_buf()
+    }
+}
+
+fn is_changed(file: &StagedFile) -> bool {
+    file.original.as_deref() != Some(file.content.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::run_by_file;
+    use crate::apply::Applier;
+    use crate::error::{ErrorCode, PatchError};
+    use crate::logger::Logger;
+    use crate::parse::Parser;
+    use crate::test_helpers::{cleanup, make_sandbox};
+    use std::fs;
+
+    #[test]
+    fn writes_every_file_or_none() {
+        let root = make_sandbox().unwrap();
+        fs::write(root.join("a.txt"), "alpha\n").unwrap();
+        fs::write(root.join("b.txt"), "beta\n").unwrap();
+        let logger = Logger::new_for_test(1, None);
+        let applier = || Applier::new(&logger, root.clone(), false);
+
+        let failing = ">>> file: a.txt\n--- from\nalpha\n--- to\nALPHA\n<<<\n\
+                       >>> file: b.txt\n--- from\ngamma\n--- to\nGAMMA\n<<<\n";
+        let mut run = run_by_file(&Parser::new().parse(failing).unwrap(), true, applier);
+        assert!(run.results[0].is_ok() && run.results[1].is_err());
+        assert!(run.pending(false).is_empty());
+        assert_eq!(run.pending(true), ["a.txt"].map(std::path::PathBuf::from));
+        assert!(run.commit(false).is_none());
+        assert_eq!(fs::read_to_string(root.join("a.txt")).unwrap(), "alpha\n");
+
+        let passing = ">>> file: a.txt\n--- from\nalpha\n--- to\nALPHA\n<<<\n\
+                       >>> file: b.txt\n--- from\nbeta\n--- to\nBETA\n<<<\n\
+                       >>> file: new/c.txt\n--- from\n\n--- to\ngamma\n<<<\n";
+        let mut run = run_by_file(&Parser::new().parse(passing).unwrap(), true, applier);
+        let pending = run.pending(false);
+        let written = run.commit(false).unwrap().unwrap();
+        assert_eq!(pending, written);
+        assert_eq!(written.len(), 3);
+        assert_eq!(fs::read_to_string(root.join("a.txt")).unwrap(), "ALPHA\n");
+        assert_eq!(fs::read_to_string(root.join("new/c.txt")).unwrap(), "gamma");
+        assert!(fs::read_dir(&root).unwrap().all(|e| !e.unwrap().file_name().to_string_lossy().ends_with(".tmp")));
+        cleanup(&root).ok();
+    }
+
+    #[test]
+    fn spellings_of_one_file_share_its_edits() {
+        let root = make_sandbox().unwrap();
+        fs::write(root.join("a.txt"), "alpha\nbeta\n").unwrap();
+        let logger = Logger::new_for_test(1, None);
+        let patch = ">>> file: ./a.txt\n--- from\nalpha\n--- to\nALPHA\n<<<\n\
+                     >>> file: a.txt\n--- from\nbeta\n--- to\nBETA\n<<<\n";
+        let mut run = run_by_file(&Parser::new().parse(patch).unwrap(), true, || Applier::new(&logger, root.clone(), false));
+        assert!(run.results.iter().all(|r| r.is_ok()));
+        assert_eq!(run.commit(false).unwrap().unwrap().len(), 1);
+        assert_eq!(fs::read_to_string(root.join("a.txt")).unwrap(), "ALPHA\nBETA\n");
+        cleanup(&root).ok();
+    }
+
+    #[test]
+    fn typoed_new_files_in_every_group_are_refused() {
+        let root = make_sandbox().unwrap();
+        fs::create_dir_all(root.join("src/ui")).unwrap();
+        fs::write(root.join("src/ui/panel.rs"), "pub struct Panel;\n").unwrap();
+        fs::write(root.join("README.md"), "# demo\n").unwrap();
+        let logger = Logger::new_for_test(1, None);
+        let patch = ">>> file: src/ui/panl.rs\n--- from\n\n--- to\npub struct Panel;\n<<<\n\
+                     >>> file: readme.md\n--- from\n\n--- to\n# demo\n<<<\n";
+        let run = run_by_file(&Parser::new().parse(patch).unwrap(), true, || Applier::new(&logger, root.clone(), false));
+        for result in &run.results {
+            assert!(matches!(result, Err(PatchError::File { code: ErrorCode::PathNotFound, .. })), "{:?}", result.as_ref().err());
+        }
+        cleanup(&root).ok();
+    }
+
+    #[test]
+    fn later_blocks_see_earlier_edits_and_may_not_overwrite_them() {
+        let root = make_sandbox().unwrap();
+        fs::write(root.join("a.py"), "def one():\n    return 1\n\n\ndef two():\n    return 2\n").unwrap();
+        let logger = Logger::new_for_test(1, None);
+        let patch = ">>> file: a.py\n--- from\ndef one():\n    return 1\n--- to\ndef one():\n    \"\"\"One.\"\"\"\n    return 1\n<<<\n\
+                     >>> file: a.py\n--- from\n    \"\"\"One.\"\"\"\n    return 1\n--- to\n    return 11\n<<<\n\
+                     >>> file: a.py\n--- from\ndef two():\n    return 2\n--- to\ndef two():\n    return 22\n<<<\n";
+        let blocks = Parser::new().parse(patch).unwrap();
+
+        // the preview runs the same engine and sees block 1's docstring when it reaches block 2
+        let preview = run_by_file(&blocks, false, || Applier::new(&logger, root.clone(), true));
+        assert!(matches!(
+            &preview.results[1],
+            Err(PatchError::Apply { code: ErrorCode::OverlappingBlocks, message, .. }) if message.contains("lines 1-3")
+        ));
+        assert_eq!(preview.results[2].as_ref().unwrap().matched_at, "def one():\n    \"\"\"One.\"\"\"\n    return 1\n\n\n".len());
+        assert_eq!(
+            preview.content("a.py".as_ref()).unwrap(),
+            "def one():\n    \"\"\"One.\"\"\"\n    return 1\n\n\ndef two():\n    return 22\n"
+        );
+        assert!(fs::read_to_string(root.join("a.py")).unwrap().contains("return 2\n"));
+
+        let mut run = run_by_file(&blocks, false, || Applier::new(&logger, root.clone(), false));
+        assert_eq!(run.commit(true).unwrap().unwrap().len(), 1);
+        assert_eq!(fs::read_to_string(root.join("a.py")).unwrap(), preview.content("a.py".as_ref()).unwrap());
+        cleanup(&root).ok();
+    }
+}
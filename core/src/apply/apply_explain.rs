@@ -0,0 +1,121 @@
+use crate::r#match::normalize_ws_preserve_newlines;
+use similar::{capture_diff_slices, Algorithm, ChangeTag, DiffOp};
+
+/// One line where a tolerant match's file text and the patch's `from` disagree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ToleratedLine {
+    /// 1-based file line; `None` for a line only the patch has
+    pub file_line: Option<usize>,
+    /// Character runs of patch vs file: `Delete` only in `from`, `Insert` only in the file
+    pub segments: Vec<(ChangeTag, String)>,
+}
+
+/// Line-by-line, character-level alignment of `from` against the slice a non-exact
+/// tier matched (starting at 1-based `first_line`). Lines that differ only in
+/// whitespace are skipped; changed line pairs are diffed character by character.
+pub fn explain_tolerance(from: &str, matched: &str, first_line: usize) -> Vec<ToleratedLine> {
+    let from_lines: Vec<&str> = from.lines().map(|l| l.trim_end_matches('\r')).collect();
+    let file_lines: Vec<&str> = matched.lines().map(|l| l.trim_end_matches('\r')).collect();
+    let key = |l: &&str| normalize_ws_preserve_newlines(l.trim());
+    let from_keys: Vec<String> = from_lines.iter().map(key).collect();
+    let file_keys: Vec<String> = file_lines.iter().map(key).collect();
+
+    let mut out = Vec::new();
+    for op in capture_diff_slices(Algorithm::Myers, &from_keys, &file_keys) {
+        let (old, new) = match op {
+            DiffOp::Equal { .. } => continue,
+            DiffOp::Delete { old_index, old_len, new_index } => (old_index..old_index + old_len, new_index..new_index),
+            DiffOp::Insert { old_index, new_index, new_len } => (old_index..old_index, new_index..new_index + new_len),
+            DiffOp::Replace { old_index, old_len, new_index, new_len } => {
+                (old_index..old_index + old_len, new_index..new_index + new_len)
+            }
+        };
+        let paired = old.len().min(new.len());
+        for k in 0..old.len().max(new.len()) {
+            let patch = (k < old.len()).then(|| from_lines[old.start + k].trim());
+            let file = (k < new.len()).then(|| file_lines[new.start + k].trim());
+            let segments = match (patch, file) {
+                (Some(p), Some(f)) if k < paired => char_segments(p, f),
+                (Some(p), _) => vec![(ChangeTag::Delete, p.to_string())],
+                (_, Some(f)) => vec![(ChangeTag::Insert, f.to_string())],
+                (None, None) => continue,
+            };
+            out.push(ToleratedLine { file_line: file.map(|_| first_line + new.start + k), segments });
+        }
+    }
+    out
+}
+
+/// Character diff of two lines, adjacent changes of the same kind merged into one run
+fn char_segments(patch: &str, file: &str) -> Vec<(ChangeTag, String)> {
+    let a: Vec<char> = patch.chars().collect();
+    let b: Vec<char> = file.chars().collect();
+    let mut segments: Vec<(ChangeTag, String)> = Vec::new();
+    let mut push = |tag: ChangeTag, chars: &[char]| {
+        if chars.is_empty() {
+            return;
+        }
+        match segments.last_mut() {
+            Some((last, text)) if *last == tag => text.extend(chars),
+            _ => segments.push((tag, chars.iter().collect())),
+        }
+    };
+    for op in capture_diff_slices(Algorithm::Myers, &a, &b) {
+        match op {
+            DiffOp::Equal { old_index, len, .. } => push(ChangeTag::Equal, &a[old_index..old_index + len]),
+            DiffOp::Delete { old_index, old_len, .. } => push(ChangeTag::Delete, &a[old_index..old_index + old_len]),
+            DiffOp::Insert { new_index, new_len, .. } => push(ChangeTag::Insert, &b[new_index..new_index + new_len]),
+            DiffOp::Replace { old_index, old_len, new_index, new_len } => {
+                push(ChangeTag::Delete, &a[old_index..old_index + old_len]);
+                push(ChangeTag::Insert, &b[new_index..new_index + new_len]);
+            }
+        }
+    }
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::explain_tolerance;
+    use crate::apply::Applier;
+    use crate::logger::Logger;
+    use crate::parse::Parser;
+    use crate::r#match::MatchTier;
+    use crate::test_helpers::{cleanup, make_sandbox};
+    use similar::ChangeTag::{Delete, Equal, Insert};
+    use std::fs;
+
+    #[test]
+    fn aligns_changed_characters_and_skips_indentation() {
+        let from = "fn load() {\n  let usr = fetch();\n    ok(usr)\n}";
+        let file = "fn load() {\n    let user = fetch();\n    ok(usr)\n    log();\n}";
+        let tolerated = explain_tolerance(from, file, 10);
+
+        assert_eq!(tolerated.len(), 2);
+        assert_eq!(tolerated[0].file_line, Some(11));
+        assert_eq!(
+            tolerated[0].segments,
+            [(Equal, "let us".to_string()), (Insert, "e".to_string()), (Equal, "r = fetch();".to_string())]
+        );
+        assert_eq!(tolerated[1].file_line, Some(13));
+        assert_eq!(tolerated[1].segments, [(Insert, "log();".to_string())]);
+        assert!(explain_tolerance("a\nb", "a\nc", 1)[0].segments.contains(&(Delete, "b".to_string())));
+    }
+
+    #[test]
+    fn every_tolerant_tier_explains_what_it_tolerated() {
+        let root = make_sandbox().unwrap();
+        fs::write(root.join("msg.py"), "def greet():\n    print(\"hello\")\n    return 0\n").unwrap();
+        let logger = Logger::new_for_test(1, None);
+        // the patch's curly quotes only match through the confusable tier
+        let patch = ">>> file: msg.py\n--- from\ndef greet():\n    print(\u{201c}hello\u{201d})\n    return 0\n--- to\ndef greet():\n    print(\"hi\")\n    return 0\n<<<\n";
+        let blocks = Parser::new().parse(patch).unwrap();
+        let result = Applier::new(&logger, root.clone(), true).apply_block(&blocks[0]).unwrap();
+
+        assert_eq!(result.tier, MatchTier::Confusable);
+        assert_eq!(result.tolerated.len(), 1);
+        assert_eq!(result.tolerated[0].file_line, Some(2));
+        assert!(result.tolerated[0].segments.contains(&(Insert, "\"".to_string())));
+        cleanup(&root).ok();
+    }
+}
@@ -0,0 +1,122 @@
+use crate::r#match::indent_width;
+
+/// Column width of a tab when comparing space- and tab-indented text
+const TAB_WIDTH: usize = 4;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum IndentStyle {
+    Spaces,
+    Tabs,
+}
+
+/// Shift every line of `to` by the indentation delta between `from` and the
+/// slice it matched via the relative-indent tier. Nesting inside `to` is kept,
+/// rescaled to the file's indent unit and rendered in the file's tab/space style.
+pub fn reindent(to: &str, from: &str, matched: &str) -> String {
+    let (Some(from_base), Some(file_base)) = (base_indent(from), base_indent(matched)) else {
+        return to.to_string();
+    };
+
+    let from_style = indent_style(from).unwrap_or(IndentStyle::Spaces);
+    let file_style = indent_style(matched).unwrap_or(from_style);
+    if from_base == file_base && from_style == file_style {
+        return to.to_string();
+    }
+
+    let from_unit = indent_unit(from, from_base).unwrap_or(TAB_WIDTH);
+    let file_unit = match file_style {
+        IndentStyle::Tabs => TAB_WIDTH,
+        IndentStyle::Spaces => indent_unit(matched, file_base).unwrap_or(from_unit),
+    };
+
+    let from_col = indent_width(from_base) as i64;
+    let file_col = indent_width(file_base) as i64;
+
+    let mut out = String::with_capacity(to.len());
+    for line in to.split_inclusive('\n') {
+        if line.trim().is_empty() {
+            out.push_str(line);
+            continue;
+        }
+        let ws = leading_ws(line);
+        let rel = indent_width(ws) as i64 - from_col;
+        let scaled = rel * file_unit as i64 / from_unit as i64;
+        let target = (file_col + scaled).max(0) as usize;
+        out.push_str(&render_indent(target, file_style));
+        out.push_str(&line[ws.len()..]);
+    }
+    out
+}
+
+fn leading_ws(line: &str) -> &str {
+    let body = line.trim_start_matches([' ', '\t']);
+    &line[..line.len() - body.len()]
+}
+
+/// Indentation of the least-indented non-blank line
+fn base_indent(s: &str) -> Option<&str> {
+    s.lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(leading_ws)
+        .min_by_key(|ws| indent_width(ws))
+}
+
+/// Smallest positive nesting step relative to `base`
+fn indent_unit(s: &str, base: &str) -> Option<usize> {
+    let base_col = indent_width(base);
+    s.lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| indent_width(leading_ws(l)).saturating_sub(base_col))
+        .filter(|step| *step > 0)
+        .min()
+}
+
+fn indent_style(s: &str) -> Option<IndentStyle> {
+    let mut spaces = false;
+    for line in s.lines().filter(|l| !l.trim().is_empty()) {
+        let ws = leading_ws(line);
+        if ws.contains('\t') {
+            return Some(IndentStyle::Tabs);
+        }
+        spaces |= !ws.is_empty();
+    }
+    spaces.then_some(IndentStyle::Spaces)
+}
+
+fn render_indent(cols: usize, style: IndentStyle) -> String {
+    match style {
+        IndentStyle::Spaces => " ".repeat(cols),
+        IndentStyle::Tabs => {
+            let mut s = "\t".repeat(cols / TAB_WIDTH);
+            s.push_str(&" ".repeat(cols % TAB_WIDTH));
+            s
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::reindent;
+
+    #[test]
+    fn shifts_nested_lines_by_base_delta() {
+        let from = "def f():\n    return 1";
+        let matched = "    def f():\n        return 1\n";
+        let to = "def f():\n    x = 2\n    return x";
+        assert_eq!(reindent(to, from, matched), "    def f():\n        x = 2\n        return x");
+    }
+
+    #[test]
+    fn converts_spaces_to_file_tabs() {
+        let from = "if x {\n  y();\n}";
+        let matched = "\tif x {\n\t\ty();\n\t}\n";
+        let to = "if x {\n  y();\n  z();\n}";
+        assert_eq!(reindent(to, from, matched), "\tif x {\n\t\ty();\n\t\tz();\n\t}");
+    }
+
+    #[test]
+    fn leaves_same_indent_untouched() {
+        let from = "  a\n  b";
+        assert_eq!(reindent("  a\n   c", from, "  a\n  b\n"), "  a\n   c");
+    }
+}
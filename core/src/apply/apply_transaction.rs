@@ -0,0 +1,143 @@
+use super::apply_write::{replace, write_atomic, write_error, write_temp};
+use crate::error::Result;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+
+/// A file changed while staging: what it held before (`None` if it did not exist) and what it will hold
+pub(crate) struct StagedFile {
+    pub(crate) original: Option<String>,
+    pub(crate) content: String,
+}
+
+/// Temp files first, so a failure there leaves every target untouched; then renames.
+/// Directories made for new files are removed again when the commit fails.
+pub(crate) fn commit(files: &BTreeMap<PathBuf, StagedFile>) -> Result<()> {
+    let mut temps: Vec<PathBuf> = Vec::with_capacity(files.len());
+    let mut dirs: Vec<PathBuf> = Vec::new();
+    for (path, file) in files {
+        let staged = match file.original {
+            Some(_) => write_temp(path, &file.content),
+            None => create_parents(path, &mut dirs).and_then(|_| write_temp(path, &file.content)),
+        };
+        match staged {
+            Ok(temp) => temps.push(temp),
+            Err(e) => {
+                remove_all(&temps);
+                remove_dirs(&dirs);
+                return Err(write_error("No file written; failed to stage", path, path, &e));
+            }
+        }
+    }
+
+    for (i, ((path, _), temp)) in files.iter().zip(&temps).enumerate() {
+        if let Err(e) = replace(temp, path) {
+            remove_all(&temps[i..]);
+            let unrestored = rollback(files.iter().take(i));
+            remove_dirs(&dirs);
+            let outcome = if unrestored.is_empty() {
+                format!("Rolled back {} file(s) after failing to replace", i)
+            } else {
+                format!("Could not restore {} (recover from the backup) after failing to replace", display_all(&unrestored))
+            };
+            return Err(write_error(&outcome, path, path, &e));
+        }
+    }
+    Ok(())
+}
+
+/// Put back what the replaced files held (removing those the patch created); returns the failures
+fn rollback<'f>(replaced: impl Iterator<Item = (&'f PathBuf, &'f StagedFile)>) -> Vec<PathBuf> {
+    replaced
+        .filter(|(path, file)| match &file.original {
+            Some(original) => write_atomic(path, original).is_err(),
+            None => fs::remove_file(path).is_err(),
+        })
+        .map(|(path, _)| path.clone())
+        .collect()
+}
+
+/// Create the missing directories above `path`, outermost first, recording each one made
+fn create_parents(path: &Path, created: &mut Vec<PathBuf>) -> io::Result<()> {
+    let missing: Vec<&Path> =
+        path.ancestors().skip(1).take_while(|dir| !dir.as_os_str().is_empty() && !dir.exists()).collect();
+    for dir in missing.into_iter().rev() {
+        match fs::create_dir(dir) {
+            Ok(()) => created.push(dir.to_path_buf()),
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+/// Remove directories `create_parents` made, innermost first; ones that are not empty stay
+fn remove_dirs(dirs: &[PathBuf]) {
+    for dir in dirs.iter().rev() {
+        fs::remove_dir(dir).ok();
+    }
+}
+
+fn remove_all(temps: &[PathBuf]) {
+    for temp in temps {
+        fs::remove_file(temp).ok();
+    }
+}
+
+fn display_all(paths: &[PathBuf]) -> String {
+    paths.iter().map(|p| p.display().to_string()).collect::<Vec<_>>().join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::apply::Applier;
+    use crate::logger::Logger;
+    use crate::parse::Parser;
+    use crate::test_helpers::{cleanup, make_sandbox};
+    use std::fs;
+
+    #[test]
+    fn failed_rename_restores_replaced_files() {
+        let root = make_sandbox().unwrap();
+        fs::write(root.join("a.txt"), "alpha\n").unwrap();
+        let logger = Logger::new_for_test(1, None);
+        let blocks = Parser::new()
+            .parse(">>> file: a.txt\n--- from\nalpha\n--- to\nALPHA\n<<<\n>>> file: b.txt\n--- from\n\n--- to\nbeta\n<<<\n")
+            .unwrap();
+
+        let applier = Applier::new(&logger, root.clone(), false);
+        // b.txt turns into a non-empty directory after staging, so renaming over it fails
+        let tx = {
+            let applier = applier.staging();
+            let results: Vec<_> = blocks.iter().map(|b| applier.apply_block(b)).collect();
+            assert!(results.iter().all(|r| r.is_ok()));
+            fs::create_dir_all(root.join("b.txt/inner")).unwrap();
+            super::commit(&applier.staged.take())
+        };
+        let err = tx.unwrap_err().to_string();
+        assert!(err.contains("Rolled back 1 file(s)"), "{}", err);
+        assert_eq!(fs::read_to_string(root.join("a.txt")).unwrap(), "alpha\n");
+        cleanup(&root).ok();
+    }
+
+    #[test]
+    fn failed_commit_removes_directories_it_created() {
+        let root = make_sandbox().unwrap();
+        fs::create_dir_all(root.join("src")).unwrap();
+        let logger = Logger::new_for_test(1, None);
+        let blocks = Parser::new()
+            .parse(">>> file: src/new/deep/c.txt\n--- from\n\n--- to\ngamma\n<<<\n>>> file: z.txt\n--- from\n\n--- to\nzeta\n<<<\n")
+            .unwrap();
+
+        let applier = Applier::new(&logger, root.clone(), false).staging();
+        assert!(blocks.iter().all(|b| applier.apply_block(b).is_ok()));
+        // z.txt turns into a non-empty directory after staging, so renaming over it fails
+        fs::create_dir_all(root.join("z.txt/inner")).unwrap();
+        let err = super::commit(&applier.staged.take()).unwrap_err().to_string();
+        assert!(err.contains("Rolled back 1 file(s)"), "{}", err);
+        assert!(!root.join("src/new").exists());
+        assert!(root.join("src").is_dir());
+        cleanup(&root).ok();
+    }
+}
@@ -0,0 +1,100 @@
+use crate::r#match::{indent_width, normalize_confusables, normalize_ws_preserve_newlines};
+use similar::{capture_diff_slices, Algorithm, DiffOp};
+
+/// How `from`/`to` lines are compared when deciding which lines are unchanged
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineKey {
+    /// Collapsed whitespace (tier 2)
+    Whitespace,
+    /// Nesting depth relative to `from`'s base indentation plus collapsed whitespace (tier 3)
+    RelativeIndent,
+    /// Confusable-folded, collapsed whitespace (confusable tier)
+    Confusable,
+}
+
+/// After a normalized match, put the file's exact bytes back for every `to` line
+/// that is only a normalization variant of a `from` context line, so only
+/// genuinely changed lines differ. `rendered_to` is `to` after any re-indent
+/// (same line count). Returns the text and the number of lines restored.
+pub fn restore_unchanged_lines(
+    rendered_to: &str,
+    to: &str,
+    from: &str,
+    matched: &str,
+    key_mode: LineKey,
+) -> (String, usize) {
+    let file_lines: Vec<&str> = matched.lines().collect();
+    let from_lines: Vec<&str> = from.lines().collect();
+    if file_lines.len() != from_lines.len() {
+        return (rendered_to.to_string(), 0);
+    }
+
+    let base = min_indent(from);
+    let key = |line: &str| -> String {
+        match key_mode {
+            LineKey::Whitespace => normalize_ws_preserve_newlines(line),
+            LineKey::RelativeIndent => {
+                let cols = indent_width(line).saturating_sub(base);
+                format!("{}{}", " ".repeat(cols), normalize_ws_preserve_newlines(line.trim_start_matches([' ', '\t'])))
+            }
+            LineKey::Confusable => normalize_ws_preserve_newlines(&normalize_confusables(line)),
+        }
+    };
+    let from_keys: Vec<String> = from_lines.iter().map(|l| key(l)).collect();
+    let to_keys: Vec<String> = to.lines().map(key).collect();
+
+    // to-line index -> file line with the same normalized content
+    let mut restored: Vec<Option<&str>> = vec![None; to_keys.len()];
+    for op in capture_diff_slices(Algorithm::Myers, &from_keys, &to_keys) {
+        if let DiffOp::Equal { old_index, new_index, len } = op {
+            for k in 0..len {
+                restored[new_index + k] = Some(file_lines[old_index + k]);
+            }
+        }
+    }
+
+    let mut out = String::with_capacity(rendered_to.len());
+    let mut count = 0usize;
+    for (idx, line) in rendered_to.split_inclusive('\n').enumerate() {
+        let body = line.trim_end_matches(['\r', '\n']);
+        match restored.get(idx).copied().flatten() {
+            Some(original) => {
+                if original != body {
+                    count += 1;
+                }
+                out.push_str(original);
+            }
+            None => out.push_str(body),
+        }
+        out.push_str(&line[body.len()..]);
+    }
+    (out, count)
+}
+
+fn min_indent(s: &str) -> usize {
+    s.lines().filter(|l| !l.trim().is_empty()).map(indent_width).min().unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{restore_unchanged_lines, LineKey};
+
+    #[test]
+    fn keeps_file_alignment_on_context_lines() {
+        let matched = "let a    = 1;   \nlet bb   = 2;\n";
+        let from = "let a = 1;\nlet bb = 2;";
+        let to = "let a = 1;\nlet bb = 3;";
+        let (out, n) = restore_unchanged_lines(to, to, from, matched, LineKey::Whitespace);
+        assert_eq!(out, "let a    = 1;   \nlet bb = 3;");
+        assert_eq!(n, 1);
+    }
+
+    #[test]
+    fn keeps_file_quotes_after_confusable_match() {
+        let matched = "say(\"hi\")\nstop()\n";
+        let from = "say(\u{201C}hi\u{201D})\nstop()";
+        let to = "say(\u{201C}hi\u{201D})\nhalt()";
+        let (out, _) = restore_unchanged_lines(to, to, from, matched, LineKey::Confusable);
+        assert_eq!(out, "say(\"hi\")\nhalt()");
+    }
+}
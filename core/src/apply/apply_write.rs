@@ -0,0 +1,121 @@
+use crate::error::{ErrorCode, PatchError};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+/// Replace `path` with `content` so that a crash or full disk leaves either the old
+/// file or the new one, never a truncated mix.
+pub(crate) fn write_atomic(path: &Path, content: &str) -> io::Result<()> {
+    let temp = write_temp(path, content)?;
+    replace(&temp, path).inspect_err(|_| {
+        fs::remove_file(&temp).ok();
+    })
+}
+
+/// Write `content` to `.<name>.applydiff-<pid>.tmp` beside the file `path` resolves to,
+/// flushed to disk and carrying the original's permissions; the rename stays on one filesystem.
+pub(crate) fn write_temp(path: &Path, content: &str) -> io::Result<PathBuf> {
+    let target = resolve(path);
+    let name = target.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let temp = target.with_file_name(format!(".{}.applydiff-{}.tmp", name, std::process::id()));
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let written = (|| {
+        let mut file = OpenOptions::new().write(true).create(true).truncate(true).open(&temp)?;
+        file.write_all(content.as_bytes())?;
+        if let Ok(meta) = fs::metadata(&target) {
+            file.set_permissions(meta.permissions())?;
+        }
+        file.sync_all()
+    })();
+    match written {
+        Ok(()) => Ok(temp),
+        Err(e) => {
+            fs::remove_file(&temp).ok();
+            Err(e)
+        }
+    }
+}
+
+/// Rename a temp file from `write_temp` over the file `path` resolves to, then flush the directory entry
+pub(crate) fn replace(temp: &Path, path: &Path) -> io::Result<()> {
+    let target = resolve(path);
+    fs::rename(temp, &target)?;
+    sync_dir(&target);
+    Ok(())
+}
+
+/// Error for a failed write, coded by cause so the log says why
+pub(crate) fn write_error(action: &str, shown: &Path, path: &Path, e: &io::Error) -> PatchError {
+    let (code, cause) = match e.kind() {
+        ErrorKind::StorageFull | ErrorKind::QuotaExceeded => (ErrorCode::DiskFull, "disk full"),
+        ErrorKind::PermissionDenied | ErrorKind::ReadOnlyFilesystem => {
+            (ErrorCode::PermissionDenied, "permission denied")
+        }
+        _ => (ErrorCode::FileWriteFailed, "write failed"),
+    };
+    PatchError::File {
+        code,
+        message: format!("{} {}: {} ({})", action, shown.display(), cause, e),
+        path: path.to_path_buf(),
+    }
+}
+
+/// The file a symlink points at, so the rename replaces the file and keeps the link
+fn resolve(path: &Path) -> PathBuf {
+    match fs::symlink_metadata(path) {
+        Ok(meta) if meta.file_type().is_symlink() => fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()),
+        _ => path.to_path_buf(),
+    }
+}
+
+#[cfg(unix)]
+fn sync_dir(target: &Path) {
+    if let Some(dir) = target.parent().and_then(|parent| File::open(parent).ok()) {
+        dir.sync_all().ok();
+    }
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_target: &Path) {}
+
+#[cfg(test)]
+mod tests {
+    use super::{write_atomic, write_error};
+    use crate::error::{ErrorCode, PatchError};
+    use crate::test_helpers::{cleanup, make_sandbox};
+    use std::fs;
+    use std::io;
+
+    #[cfg(unix)]
+    #[test]
+    fn keeps_permissions_and_symlinks() {
+        use std::os::unix::fs::{symlink, PermissionsExt};
+
+        let root = make_sandbox().unwrap();
+        let script = root.join("run.sh");
+        fs::write(&script, "echo old\n").unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o750)).unwrap();
+        symlink(&script, root.join("link.sh")).unwrap();
+
+        write_atomic(&root.join("link.sh"), "echo new\n").unwrap();
+        assert!(fs::symlink_metadata(root.join("link.sh")).unwrap().file_type().is_symlink());
+        assert_eq!(fs::read_to_string(&script).unwrap(), "echo new\n");
+        assert_eq!(fs::metadata(&script).unwrap().permissions().mode() & 0o777, 0o750);
+        assert_eq!(fs::read_dir(&root).unwrap().count(), 2, "temp file left behind");
+        cleanup(&root).ok();
+    }
+
+    #[test]
+    fn codes_disk_full_and_permission_denied() {
+        let code = |kind| match write_error("Failed to write", "a.txt".as_ref(), "a.txt".as_ref(), &io::Error::from(kind)) {
+            PatchError::File { code, message, .. } => (code, message),
+            other => panic!("unexpected {:?}", other),
+        };
+        let (full, message) = code(io::ErrorKind::StorageFull);
+        assert!(matches!(full, ErrorCode::DiskFull) && message.contains("disk full"), "{}", message);
+        assert!(matches!(code(io::ErrorKind::PermissionDenied).0, ErrorCode::PermissionDenied));
+        assert!(matches!(code(io::ErrorKind::Other).0, ErrorCode::FileWriteFailed));
+    }
+}
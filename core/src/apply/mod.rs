@@ -0,0 +1,702 @@
+use crate::config::{ProjectConfig, TierPolicy, CONFIG_FILE};
+use crate::error::{ErrorCode, PatchError, Result};
+use crate::locate::{find_elsewhere, project_files, resolve_path, PathGuess, Relocation, WalkLimits};
+use crate::logger::Logger;
+use crate::r#match::{
+    code_lines, comment_syntax, confusables_in, find_exact_occurrences, find_scope, indent_scoped, normalize_confusables,
+    AssumedSpan, CommentSyntax, LineIndex, MatchOptions, MatchPipeline, MatchResult, MatchTier, NearMiss, Proximity,
+};
+use crate::parse::{MatchMode, Occurrence, PatchBlock, DEFAULT_FUZZ};
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::ErrorKind;
+use std::ops::Range;
+use std::path::{Component, Path, PathBuf};
+use std::rc::Rc;
+use std::sync::{Arc, OnceLock};
+
+mod apply_align;
+mod apply_anchor;
+mod apply_engine;
+mod apply_explain;
+mod apply_indent;
+mod apply_transaction;
+mod apply_write;
+mod apply_whitespace;
+
+pub use apply_align::{merge_aligned, merge_aligned_keyed, Merged};
+pub use apply_anchor::keep_assumed_middle;
+pub use apply_explain::{explain_tolerance, ToleratedLine};
+pub use apply_indent::reindent;
+pub use apply_engine::{run_by_file, PatchRun};
+pub use apply_whitespace::{restore_unchanged_lines, LineKey};
+
+use apply_transaction::StagedFile;
+
+pub struct ApplyResult {
+    /// File the block was applied to (differs from the patch's path after a confirmed relocation)
+    pub file: PathBuf,
+    pub matched_at: usize,
+    pub matched_end: usize,
+    pub score: f64,
+    /// Tier that located the block (`Exact` for appends and explicit occurrences)
+    pub tier: MatchTier,
+    /// Gap to the runner-up fuzzy candidate (`None` for non-fuzzy matches)
+    pub margin: Option<f64>,
+    /// Number of ranges replaced (>1 only for `all=true`)
+    pub occurrences: usize,
+    /// Text that now occupies the `matched_at..matched_end` span (for previews)
+    pub replacement: String,
+    /// File lines between anchors that the patch's `from` did not match (anchor-sandwich tier)
+    pub assumed: Option<AssumedSpan>,
+    /// What the matcher tolerated (e.g. comments that differed), for previews
+    pub notes: Vec<String>,
+    /// Lines where the file differs from `from`, character by character (empty for exact matches)
+    pub tolerated: Vec<ToleratedLine>,
+    /// The block as it applies verbatim: `from` is the file text that was replaced and
+    /// `to` what replaced it (first occurrence), pinned with `match=no-fuzzy` and `line=`
+    pub canonical: PatchBlock,
+}
+
+pub struct Applier<'a> {
+    logger: &'a Logger,
+    root: PathBuf,
+    dry_run: bool,
+    pipeline: MatchPipeline,
+    config: ProjectConfig,
+    /// 0-based start line of the previous match per file (full path; proximity prior for the next block)
+    last_match_line: RefCell<HashMap<PathBuf, usize>>,
+    /// Patch path -> project file the user confirmed the block belongs to
+    relocations: HashMap<PathBuf, PathBuf>,
+    /// Relocations proposed by the cross-file search, awaiting confirmation
+    suggested: RefCell<Vec<Relocation>>,
+    /// Line index of each file as last read; reused while the content is unchanged
+    indexes: RefCell<HashMap<PathBuf, Rc<LineIndex>>>,
+    /// Best rejected fuzzy candidate of the last block, if it matched nothing
+    near_miss: RefCell<Option<NearMiss>>,
+    /// Keep new contents in `staged` instead of writing them (see `run_by_file`)
+    staging: bool,
+    /// Content of each file read or changed so far while staging, by full path
+    staged: RefCell<BTreeMap<PathBuf, StagedFile>>,
+    /// Byte ranges of each file (full path) that earlier blocks replaced, in current offsets
+    edited: RefCell<HashMap<PathBuf, Vec<Range<usize>>>>,
+    /// Project files, walked once on first need and shared by the appliers of one run
+    listing: Arc<OnceLock<Vec<PathBuf>>>,
+}
+
+impl<'a> Applier<'a> {
+    pub fn new(logger: &'a Logger, root: PathBuf, dry_run: bool) -> Self {
+        Self {
+            logger,
+            root,
+            dry_run,
+            pipeline: MatchPipeline::default(),
+            config: ProjectConfig::default(),
+            last_match_line: RefCell::new(HashMap::new()),
+            relocations: HashMap::new(),
+            suggested: RefCell::new(Vec::new()),
+            indexes: RefCell::new(HashMap::new()),
+            near_miss: RefCell::new(None),
+            staging: false,
+            staged: RefCell::new(BTreeMap::new()),
+            edited: RefCell::new(HashMap::new()),
+            listing: Arc::new(OnceLock::new()),
+        }
+    }
+
+    /// Use project defaults (e.g. loaded from `.applydiff.json`).
+    pub fn with_config(mut self, config: ProjectConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Replace the default tier pipeline (reorder, disable or add tiers).
+    pub fn with_pipeline(mut self, pipeline: MatchPipeline) -> Self {
+        self.pipeline = pipeline;
+        self
+    }
+
+    /// Apply blocks naming `named` to `found` instead (relocations the user confirmed).
+    pub fn with_relocations(mut self, relocations: Vec<Relocation>) -> Self {
+        self.relocations = relocations.into_iter().map(|r| (r.named, r.found)).collect();
+        self
+    }
+
+    /// Relocations the cross-file search proposed so far; none of them were applied.
+    pub fn suggested_relocations(&self) -> Vec<Relocation> {
+        self.suggested.borrow().clone()
+    }
+
+    /// Read each file once and stage writes in memory; later blocks read the staged contents
+    pub(crate) fn staging(mut self) -> Self {
+        self.staging = true;
+        self
+    }
+
+    /// Share the project listing with the other appliers of a run.
+    pub(crate) fn sharing_listing(mut self, listing: &Arc<OnceLock<Vec<PathBuf>>>) -> Self {
+        self.listing = Arc::clone(listing);
+        self
+    }
+
+    /// File a block will be applied to, after confirmed relocations
+    pub(crate) fn target_of<'b>(&'b self, blk: &'b PatchBlock) -> &'b Path {
+        self.relocations.get(&blk.file).unwrap_or(&blk.file)
+    }
+
+    /// Best fuzzy candidate of the last `apply_block` call, when it failed to match;
+    /// carries the threshold a retry could use.
+    pub fn last_near_miss(&self) -> Option<NearMiss> {
+        self.near_miss.borrow().clone()
+    }
+
+    pub fn apply_block(&self, blk: &PatchBlock) -> Result<ApplyResult> {
+        self.near_miss.replace(None);
+        if let Some(found) = self.relocations.get(&blk.file) {
+            let moved = PatchBlock { file: found.clone(), ..blk.clone() };
+            let mut result = self.apply_in_file(&moved)?;
+            result.notes.push(format!("applied to {} instead of {} (confirmed)", found.display(), blk.file.display()));
+            return Ok(result);
+        }
+        match self.apply_in_file(blk) {
+            Err(e) if self.config.search_other_files && !blk.from.trim().is_empty() && is_relocatable(&e) => {
+                Err(self.suggest_relocation(blk).unwrap_or(e))
+            }
+            other => other,
+        }
+    }
+
+    /// Look for `from` in the rest of the project; a unique hit becomes a suggestion
+    /// that must be confirmed (`with_relocations`) before anything is written there.
+    fn suggest_relocation(&self, blk: &PatchBlock) -> Option<PatchError> {
+        let found = find_elsewhere(&self.root, &blk.file, &blk.from, &self.pipeline, WalkLimits::default(), self.logger)?;
+        let message = format!(
+            "FROM not found in {}, but it matches uniquely in {}; confirm the relocation to apply it there",
+            blk.file.display(),
+            found.display()
+        );
+        let relocation = Relocation { named: blk.file.clone(), found };
+        let mut suggested = self.suggested.borrow_mut();
+        if !suggested.contains(&relocation) {
+            suggested.push(relocation);
+        }
+        Some(PatchError::Apply { code: ErrorCode::RelocationSuggested, message, file: blk.file.clone() })
+    }
+
+    /// Fail with a "did you mean" when a file about to be created looks like a
+    /// mistyped path of an existing one: a leftover diff prefix, other letter case,
+    /// or a few typos away in the same directory. A shared file name alone is no
+    /// slip (`docs/README.md` beside `README.md`).
+    fn refuse_near_miss_path(&self, blk: &PatchBlock) -> Result<()> {
+        let files = self.listing.get_or_init(|| project_files(&self.root, WalkLimits::default(), self.logger));
+        let Some((existing, guess)) = resolve_path(&blk.file, files) else {
+            return Ok(());
+        };
+        let slip = match guess {
+            PathGuess::Prefix | PathGuess::Case => true,
+            PathGuess::EditDistance => existing.parent() == blk.file.parent(),
+            PathGuess::Basename => false,
+        };
+        if !slip {
+            return Ok(());
+        }
+        self.logger.info(
+            "applier",
+            "path_near_miss",
+            &format!("{} does not exist; closest is {} ({:?})", blk.file.display(), existing.display(), guess),
+        );
+        Err(PatchError::File {
+            code: ErrorCode::PathNotFound,
+            message: format!(
+                "{} does not exist; did you mean {}? (add `| create=true` / `Create: true` to create it anyway)",
+                blk.file.display(),
+                existing.display()
+            ),
+            path: self.root.join(&blk.file),
+        })
+    }
+
+    fn apply_in_file(&self, blk: &PatchBlock) -> Result<ApplyResult> {
+        // harden: disallow absolute paths or '..' traversal
+        if blk.file.is_absolute() || blk.file.components().any(|c| matches!(c, Component::ParentDir)) {
+            return Err(PatchError::Validation {
+                code: ErrorCode::ValidationFailed,
+                message: "Patch path escapes target directory".to_string(),
+                context: blk.file.display().to_string(),
+            });
+        }
+
+        let path = file_key(&self.root, &blk.file);
+
+        // read file, allow append-create if FROM is empty
+        let content = match self.read_file(&path) {
+            Ok(s) => s,
+            Err(e) => {
+                if blk.from.trim().is_empty() && e.kind() == ErrorKind::NotFound {
+                    // a typo'd path would otherwise create a stray file
+                    if !blk.create {
+                        self.refuse_near_miss_path(blk)?;
+                    }
+                    String::new()
+                } else {
+                    return Err(PatchError::File {
+                        code: ErrorCode::FileReadFailed,
+                        message: format!("Failed to read {}: {}", blk.file.display(), e),
+                        path: path.clone(),
+                    });
+                }
+            }
+        };
+
+        // append/create when FROM is empty
+        if blk.from.trim().is_empty() {
+            let mut new_content = content.clone();
+
+            // FIX: only insert a separator newline when appending to a NON-empty file that lacks one.
+            if !new_content.is_empty() && !new_content.ends_with('\n') && !blk.to.is_empty() {
+                new_content.push('\n');
+            }
+            new_content.push_str(&blk.to);
+            let at = content.len();
+            let replacement = new_content[at..].to_string();
+
+            self.write_file(blk, &path, new_content)?;
+            record_edits(self.edited.borrow_mut().entry(path.clone()).or_default(), &[(at..at, replacement.len())]);
+
+            return Ok(ApplyResult {
+                file: blk.file.clone(),
+                matched_at: at,
+                matched_end: at,
+                score: 1.0,
+                tier: MatchTier::Exact,
+                margin: None,
+                occurrences: 1,
+                replacement,
+                assumed: None,
+                notes: Vec::new(),
+                tolerated: Vec::new(),
+                canonical: blk.clone(),
+            });
+        }
+
+        // `Within:` limits the search to the block its anchor opens
+        let (scope_start, haystack) = match &blk.within {
+            Some(anchor) => {
+                let (start, end) = find_scope(&content, anchor, self.logger).ok_or_else(|| PatchError::Apply {
+                    code: ErrorCode::NoMatch,
+                    message: format!("Scope '{}' not found, or not unique in the file", anchor),
+                    file: blk.file.clone(),
+                })?;
+                (start, &content[start..end])
+            }
+            None => (0, content.as_str()),
+        };
+        let scope_line = content[..scope_start].matches('\n').count();
+
+        // find match (exact or fuzzy), or the explicitly requested exact occurrence(s)
+        let matches = match blk.occurrence {
+            Occurrence::Unique => {
+                let opts = MatchOptions {
+                    min_score: blk.fuzz,
+                    margin: blk.margin.unwrap_or(self.config.margin),
+                    mode: blk.match_mode,
+                    proximity: self.proximity_for(blk, &path).map(|p| p.relative_to(scope_line)),
+                    comments: self.comment_syntax_for(blk),
+                    indent_scoped: indent_scoped(&blk.file),
+                    parallel: self.config.parallel,
+                    metric: blk.metric.unwrap_or(self.config.fuzzy_metric),
+                };
+                // a scope is a one-off slice; the whole file's index serves every later block
+                let index = match blk.within {
+                    Some(_) => Rc::new(LineIndex::new(haystack)),
+                    None => self.line_index(&blk.file, haystack),
+                };
+                match self.pipeline.find_explained(haystack, &index, &blk.from, &opts, self.logger) {
+                    Ok(m) => Some(vec![m]),
+                    Err(miss) => {
+                        *self.near_miss.borrow_mut() = miss.map(|m| NearMiss {
+                            first_line: m.first_line + scope_line,
+                            last_line: m.last_line + scope_line,
+                            ..m
+                        });
+                        None
+                    }
+                }
+            }
+            occurrence => find_exact_occurrences(haystack, &blk.from, occurrence, self.logger),
+        };
+        let matches = matches.map(|found| {
+            found.into_iter().map(|m| shift_match(m, scope_start, scope_line)).collect::<Vec<_>>()
+        });
+        let Some(matches) = matches else {
+            let message = match &*self.near_miss.borrow() {
+                Some(miss) => format!("Could not match block: {}", miss),
+                None => "Could not match block: either no suitable match found, or multiple ambiguous matches detected. Check logs for details.".to_string(),
+            };
+            return Err(PatchError::Apply { code: ErrorCode::NoMatch, message, file: blk.file.clone() });
+        };
+
+        let tier = matches[0].tier;
+        let mut notes = matches[0].notes.clone();
+        if tier != MatchTier::Exact {
+            match self.config.tier_policy {
+                TierPolicy::Allow => {}
+                TierPolicy::WarnUnlessExact => {
+                    self.logger.info("applier", "tier_not_exact", &format!("block matched by the {} tier", tier));
+                    notes.push(format!("not an exact match: located by the {} tier", tier));
+                }
+                TierPolicy::RequireExact => {
+                    self.logger.info("applier", "tier_rejected", &format!("block matched by the {} tier", tier));
+                    return Err(PatchError::Apply {
+                        code: ErrorCode::TierRejected,
+                        message: format!(
+                            "Block only matched by the {} tier; tier_policy in {} requires an exact match",
+                            tier, CONFIG_FILE
+                        ),
+                        file: blk.file.clone(),
+                    });
+                }
+            }
+        }
+
+        if let Some(earlier) = self.overlapped_edit(&path, &matches) {
+            let (from_line, to_line) = line_span(&content, &earlier);
+            self.logger.info("applier", "overlapping_blocks", &format!("lines {}-{} already changed", from_line, to_line));
+            return Err(PatchError::Apply {
+                code: ErrorCode::OverlappingBlocks,
+                message: format!(
+                    "Block overlaps lines {}-{}, which an earlier block in this patch already changed; merge the two blocks",
+                    from_line, to_line
+                ),
+                file: blk.file.clone(),
+            });
+        }
+
+        // splice back-to-front so earlier offsets stay valid
+        let mut new_content = content.clone();
+        let mut first_to = String::new();
+        let mut spliced = Vec::with_capacity(matches.len());
+        for m in matches.iter().rev() {
+            let matched_slice = &content[m.start..m.end];
+            let to_text = match m.tier {
+                // `from` was written at another indentation level; shift `to` to match the file,
+                // then keep the file's bytes on untouched context lines
+                MatchTier::RelativeIndent => {
+                    let shifted = reindent(&blk.to, &blk.from, matched_slice);
+                    self.restore_context(&shifted, blk, matched_slice, LineKey::RelativeIndent)
+                }
+                MatchTier::Whitespace => self.restore_context(&blk.to, blk, matched_slice, LineKey::Whitespace),
+                // the model substituted confusables: undo the same substitutions on changed lines
+                MatchTier::Confusable => {
+                    let unfolded = fold_substituted_confusables(&blk.to, &blk.from, matched_slice);
+                    self.restore_context(&unfolded, blk, matched_slice, LineKey::Confusable)
+                }
+                // comments/blank lines differ: replay the edit on code lines, keep the file's comments
+                MatchTier::CommentInsensitive => match self.comment_syntax_for(blk) {
+                    Some(syntax) => {
+                        let merged = merge_aligned_keyed(&blk.to, &blk.from, matched_slice, |t| code_lines(t, syntax));
+                        if merged.kept > 0 {
+                            self.logger.info("applier", "comment_lines_kept", &format!("{} file-only line(s) kept", merged.kept));
+                        }
+                        merged.text
+                    }
+                    None => blk.to.clone(),
+                },
+                // lines were dropped or added around the block: replay the edit, keep file-only lines
+                MatchTier::Aligned => {
+                    let merged = merge_aligned(&blk.to, &blk.from, matched_slice);
+                    self.keep_file_only_lines(blk, merged, line_span(&content, &(m.start..m.end)))?
+                }
+                // a wider window than `from` (adaptive sizing): don't delete the lines it left out
+                MatchTier::Fuzzy if matched_slice.lines().count() > blk.from.lines().count() => {
+                    let merged = merge_aligned(&blk.to, &blk.from, matched_slice);
+                    self.keep_file_only_lines(blk, merged, line_span(&content, &(m.start..m.end)))?
+                }
+                // the model paraphrased the middle: keep the file's lines there unless `to` edits them
+                MatchTier::AnchorSandwich => match &m.assumed {
+                    Some(assumed) => self.keep_middle(blk, assumed)?,
+                    None => blk.to.clone(),
+                },
+                _ => blk.to.clone(),
+            };
+            let to_text = harmonize_eol(&to_text, matched_slice);
+            new_content.replace_range(m.start..m.end, &to_text);
+            spliced.push((m.start..m.end, to_text.len()));
+            // back-to-front: the last assignment is the first match's
+            first_to = to_text;
+        }
+
+        let (first, last) = (&matches[0], &matches[matches.len() - 1]);
+        let new_end = last.end + new_content.len() - content.len();
+        let replacement = new_content[first.start..new_end].to_string();
+        // lines before the match are untouched, so this line is valid before and after the write
+        let first_line = content[..first.start].matches('\n').count();
+        self.last_match_line.borrow_mut().insert(path.clone(), first_line);
+        let canonical = PatchBlock {
+            from: content[first.start..first.end].to_string(),
+            to: first_to,
+            fuzz: DEFAULT_FUZZ,
+            margin: None,
+            match_mode: match blk.match_mode {
+                MatchMode::ExactOnly => MatchMode::ExactOnly,
+                _ => MatchMode::NoFuzzy,
+            },
+            line_hint: Some(first_line + 1),
+            metric: None,
+            ..blk.clone()
+        };
+
+        // every tier past exact tolerated something; whitespace-only differences explain to nothing
+        let tolerated = if tier != MatchTier::Exact {
+            let tolerated = explain_tolerance(&blk.from, &content[first.start..first.end], first_line + 1);
+            self.logger.info(
+                "applier",
+                "match_tolerated",
+                &format!("{} line(s) differ from the patch's FROM (tier: {})", tolerated.len(), tier),
+            );
+            tolerated
+        } else {
+            Vec::new()
+        };
+
+        self.write_file(blk, &path, new_content)?;
+        record_edits(self.edited.borrow_mut().entry(path.clone()).or_default(), &spliced);
+
+        Ok(ApplyResult {
+            file: blk.file.clone(),
+            matched_at: first.start,
+            matched_end: last.end,
+            score: first.score,
+            tier,
+            margin: first.margin,
+            occurrences: matches.len(),
+            replacement,
+            assumed: first.assumed.clone(),
+            notes,
+            tolerated,
+            canonical,
+        })
+    }
+
+    /// Current content of `path`: the staged version while staging (read from disk once)
+    fn read_file(&self, path: &Path) -> std::io::Result<String> {
+        if let Some(staged) = self.staged.borrow().get(path) {
+            return Ok(staged.content.clone());
+        }
+        let content = fs::read_to_string(path)?;
+        if self.staging {
+            let original = Some(content.clone());
+            self.staged.borrow_mut().insert(path.to_path_buf(), StagedFile { original, content: content.clone() });
+        }
+        Ok(content)
+    }
+
+    /// A range an earlier block replaced that one of `matches` would overwrite
+    fn overlapped_edit(&self, path: &Path, matches: &[MatchResult]) -> Option<Range<usize>> {
+        let edited = self.edited.borrow();
+        let earlier = edited.get(path)?;
+        matches.iter().find_map(|m| {
+            earlier
+                .iter()
+                .find(|r| if r.is_empty() { m.start < r.start && r.start < m.end } else { m.start < r.end && r.start < m.end })
+                .cloned()
+        })
+    }
+
+    /// Write `content` to `path` crash-safely, or stage it; a dry run writes nothing
+    fn write_file(&self, blk: &PatchBlock, path: &Path, content: String) -> Result<()> {
+        if self.staging {
+            let mut staged = self.staged.borrow_mut();
+            match staged.get_mut(path) {
+                Some(file) => file.content = content,
+                None => {
+                    let original = fs::read_to_string(path).ok();
+                    staged.insert(path.to_path_buf(), StagedFile { original, content });
+                }
+            }
+            return Ok(());
+        }
+        if self.dry_run {
+            return Ok(());
+        }
+        apply_write::write_atomic(path, &content).map_err(|e| {
+            let err = apply_write::write_error("Failed to write", &blk.file, path, &e);
+            self.logger.info("applier", "write_failed", &err.to_string());
+            err
+        })
+    }
+
+    /// Index of `content`, rebuilt only when the file changed since the last block
+    fn line_index(&self, file: &Path, content: &str) -> Rc<LineIndex> {
+        let mut cache = self.indexes.borrow_mut();
+        if let Some(index) = cache.get(file).filter(|index| index.is_for(content)) {
+            self.logger.info("applier", "line_index_reused", &file.display().to_string());
+            return Rc::clone(index);
+        }
+        let index = Rc::new(LineIndex::new(content));
+        cache.insert(file.to_path_buf(), Rc::clone(&index));
+        index
+    }
+
+    /// An explicit line hint wins; otherwise prefer candidates after the previous block in this file.
+    fn proximity_for(&self, blk: &PatchBlock, path: &Path) -> Option<Proximity> {
+        blk.line_hint
+            .map(|line| Proximity::Near(line.saturating_sub(1)))
+            .or_else(|| self.last_match_line.borrow().get(path).map(|&line| Proximity::After(line)))
+    }
+
+    /// Comment syntax for the comment-insensitive tier, when the project opts in
+    fn comment_syntax_for(&self, blk: &PatchBlock) -> Option<CommentSyntax> {
+        if !self.config.ignore_comments {
+            return None;
+        }
+        comment_syntax(&blk.file)
+    }
+
+    /// The merged text, logging the file-only lines it kept; fails when a changed line found
+    /// no partner in the matched lines while file-only lines stay, as one of those may be
+    /// its stale original
+    fn keep_file_only_lines(&self, blk: &PatchBlock, merged: Merged, (first, last): (usize, usize)) -> Result<String> {
+        if merged.kept > 0 && merged.unplaced > 0 {
+            self.logger.info(
+                "applier",
+                "merge_rejected",
+                &format!("{} changed line(s) match none of lines {}-{}", merged.unplaced, first, last),
+            );
+            return Err(PatchError::Apply {
+                code: ErrorCode::TierRejected,
+                message: format!(
+                    "Block matched lines {}-{}, which hold lines its FROM lacks, and {} of its changed line(s) match none of them; resend the block with the current text of those lines",
+                    first, last, merged.unplaced
+                ),
+                file: blk.file.clone(),
+            });
+        }
+        if merged.kept > 0 {
+            self.logger.info("applier", "aligned_lines_kept", &format!("{} file-only line(s) kept", merged.kept));
+        }
+        Ok(merged.text)
+    }
+
+    /// `to` with the file's middle put back; fails when `to` edits a middle the patch never saw
+    fn keep_middle(&self, blk: &PatchBlock, assumed: &AssumedSpan) -> Result<String> {
+        match keep_assumed_middle(&blk.to, &blk.from, assumed) {
+            Some(text) => {
+                self.logger.info(
+                    "applier",
+                    "assumed_middle_kept",
+                    &format!("file lines {}-{} kept in place of the patch's middle", assumed.first_line, assumed.last_line),
+                );
+                Ok(text)
+            }
+            None => {
+                self.logger.info("applier", "assumed_middle_edited", "replacement edits the assumed middle; block refused");
+                Err(PatchError::Apply {
+                    code: ErrorCode::TierRejected,
+                    message: format!(
+                        "Block only matched by its first and last lines, and its replacement edits lines {}-{}, which differ from the patch's FROM; resend the block with the current text of those lines",
+                        assumed.first_line, assumed.last_line
+                    ),
+                    file: blk.file.clone(),
+                })
+            }
+        }
+    }
+
+    fn restore_context(&self, rendered_to: &str, blk: &PatchBlock, matched_slice: &str, key: LineKey) -> String {
+        let (text, restored) = restore_unchanged_lines(rendered_to, &blk.to, &blk.from, matched_slice, key);
+        if restored > 0 {
+            self.logger.info("applier", "whitespace_restored", &format!("{} context line(s) kept in file style", restored));
+        }
+        text
+    }
+}
+
+/// Full path of `file` under `root`, the same however the patch spells it: `.`
+/// segments dropped and, for a file on disk, the letter case it has there
+pub(crate) fn file_key(root: &Path, file: &Path) -> PathBuf {
+    let joined = root.join(file.components().filter(|c| !matches!(c, Component::CurDir)).collect::<PathBuf>());
+    // a symlink keeps its own name; the write resolves it
+    if fs::symlink_metadata(&joined).is_ok_and(|meta| !meta.file_type().is_symlink()) {
+        if let (Ok(real), Ok(real_root)) = (joined.canonicalize(), root.canonicalize()) {
+            if let Ok(inside) = real.strip_prefix(&real_root) {
+                return root.join(inside);
+            }
+        }
+    }
+    joined
+}
+
+/// Failures that a wrong path in the patch would explain
+fn is_relocatable(e: &PatchError) -> bool {
+    matches!(
+        e,
+        PatchError::File { code: ErrorCode::FileReadFailed, .. } | PatchError::Apply { code: ErrorCode::NoMatch, .. }
+    )
+}
+
+/// Translate a match found inside a scope back to whole-file offsets and lines.
+fn shift_match(mut m: MatchResult, bytes: usize, lines: usize) -> MatchResult {
+    m.start += bytes;
+    m.end += bytes;
+    if let Some(assumed) = m.assumed.as_mut() {
+        assumed.first_line += lines;
+        assumed.last_line += lines;
+    }
+    m
+}
+
+/// Shift `edited` past replacements of `old` ranges by `new_len` bytes (given back-to-front)
+/// and add the replaced spans.
+fn record_edits(edited: &mut Vec<Range<usize>>, spliced: &[(Range<usize>, usize)]) {
+    for (old, new_len) in spliced {
+        for r in edited.iter_mut().filter(|r| r.start >= old.end) {
+            *r = r.start + new_len - old.len()..r.end + new_len - old.len();
+        }
+        edited.push(old.start..old.start + new_len);
+    }
+}
+
+/// 1-based first and last line of a byte range of `content`
+fn line_span(content: &str, range: &Range<usize>) -> (usize, usize) {
+    let first = content[..range.start].matches('\n').count() + 1;
+    let last = first + content[range.start..range.end].trim_end_matches('\n').matches('\n').count();
+    (first, last)
+}
+
+/// Fold, in `to`, the confusables that appear in `from` but not in the matched file text.
+fn fold_substituted_confusables(to: &str, from: &str, matched_slice: &str) -> String {
+    let in_file = confusables_in(matched_slice);
+    let mut out = to.to_string();
+    for ch in confusables_in(from).into_iter().filter(|c| !in_file.contains(c)) {
+        out = out.replace(ch, &normalize_confusables(&ch.to_string()));
+    }
+    out
+}
+
+/// Harmonize the trailing EOL of `to` with the matched slice.
+fn harmonize_eol(to: &str, matched_slice: &str) -> String {
+    let matched_nl = if matched_slice.ends_with("\r\n") {
+        "\r\n"
+    } else if matched_slice.ends_with('\n') {
+        "\n"
+    } else {
+        ""
+    };
+
+    let mut to_text = to.to_string();
+    if !matched_nl.is_empty() {
+        if to_text.ends_with("\r\n") && matched_nl == "\n" {
+            to_text.truncate(to_text.len().saturating_sub(2));
+            to_text.push('\n');
+        } else if to_text.ends_with('\n') && matched_nl == "\r\n" {
+            to_text.pop();
+            to_text.push_str("\r\n");
+        } else if !to_text.ends_with('\n') && !to_text.ends_with("\r\n") {
+            to_text.push_str(matched_nl);
+        }
+    }
+    to_text
+}
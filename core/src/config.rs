@@ -0,0 +1,82 @@
+use crate::error::{ErrorCode, PatchError, Result};
+use crate::parse::FuzzyMetric;
+use serde::Deserialize;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+
+/// Project-level settings file, looked up in the target directory root.
+pub const CONFIG_FILE: &str = ".applydiff.json";
+
+/// Minimum gap between the best and runner-up fuzzy scores before a match is
+/// accepted; smaller gaps are rejected as ambiguous.
+pub const DEFAULT_AMBIGUITY_MARGIN: f64 = 0.02;
+
+/// What to do when a block only matched through a forgiving tier
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TierPolicy {
+    /// Accept any tier silently
+    #[default]
+    Allow,
+    /// Accept, but flag every non-exact match in the preview/apply output
+    WarnUnlessExact,
+    /// Reject non-exact matches; the file is left untouched
+    RequireExact,
+}
+
+/// Project defaults; every field can be overridden per block.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProjectConfig {
+    /// Ambiguity margin for fuzzy matches (`Margin:` / `margin=` per block)
+    pub margin: f64,
+    /// Enable the comment- and blank-line-insensitive tier for files with known comment syntax
+    pub ignore_comments: bool,
+    /// Policy for matches found by any tier other than exact
+    pub tier_policy: TierPolicy,
+    /// When a block's `from` is not in the named file, look for it in the rest of the project
+    pub search_other_files: bool,
+    /// Score fuzzy windows and preview files on all cores
+    pub parallel: bool,
+    /// Fuzzy scoring metric (`Metric:` / `metric=` per block)
+    pub fuzzy_metric: FuzzyMetric,
+}
+
+impl Default for ProjectConfig {
+    fn default() -> Self {
+        Self {
+            margin: DEFAULT_AMBIGUITY_MARGIN,
+            ignore_comments: false,
+            tier_policy: TierPolicy::Allow,
+            search_other_files: false,
+            parallel: true,
+            fuzzy_metric: FuzzyMetric::default(),
+        }
+    }
+}
+
+impl ProjectConfig {
+    /// Load `<root>/.applydiff.json`; a missing file yields the defaults.
+    pub fn load(root: &Path) -> Result<Self> {
+        let path = root.join(CONFIG_FILE);
+        let text = match fs::read_to_string(&path) {
+            Ok(t) => t,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => {
+                return Err(PatchError::File {
+                    code: ErrorCode::FileReadFailed,
+                    message: format!("Failed to read {}: {}", CONFIG_FILE, e),
+                    path,
+                })
+            }
+        };
+        let mut config: Self = serde_json::from_str(&text).map_err(|e| PatchError::Parse {
+            code: ErrorCode::ParseFailed,
+            message: format!("Invalid {}: {}", CONFIG_FILE, e),
+            context: path.display().to_string(),
+        })?;
+        config.margin = config.margin.clamp(0.0, 1.0);
+        Ok(config)
+    }
+}
@@ -8,10 +8,16 @@ pub enum ErrorCode {
     // Parse / matching
     ParseFailed,
     NoMatch,
+    TierRejected,
+    RelocationSuggested,
+    PathNotFound,
+    OverlappingBlocks,
 
     // File I/O
     FileReadFailed,
     FileWriteFailed,
+    DiskFull,
+    PermissionDenied,
 
     // Validation / bounds
     ValidationFailed,
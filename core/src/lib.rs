@@ -2,7 +2,9 @@
 
 pub mod apply;
 pub mod backup;
+pub mod config;
 pub mod error;
+pub mod locate;
 pub mod test_runner;
 pub mod test_helpers;
 pub mod logger;
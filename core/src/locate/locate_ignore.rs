@@ -0,0 +1,128 @@
+use std::fs;
+use std::path::Path;
+
+/// One `.gitignore` line, anchored to the directory holding the file
+#[derive(Debug, Clone)]
+struct Rule {
+    /// Directory of the `.gitignore`, relative to the project root ("" at the root)
+    base: String,
+    pattern: String,
+    /// Pattern contains a `/` (other than a trailing one): match the whole relative path
+    anchored: bool,
+    dir_only: bool,
+    negated: bool,
+}
+
+impl Rule {
+    fn parse(base: &str, line: &str) -> Option<Self> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let (negated, line) = line.strip_prefix('!').map_or((false, line), |rest| (true, rest));
+        let (dir_only, line) = line.strip_suffix('/').map_or((false, line), |rest| (true, rest));
+        Some(Self {
+            base: base.to_string(),
+            pattern: line.trim_start_matches('/').to_string(),
+            anchored: line.contains('/'),
+            dir_only,
+            negated,
+        })
+    }
+}
+
+/// The subset of gitignore semantics the project search needs: `*`, `?`, `**`,
+/// leading/inner `/` anchoring, trailing `/` for directories and `!` negation
+/// (the last matching rule wins). Rules accumulate as the walk descends.
+#[derive(Debug, Clone, Default)]
+pub struct IgnoreRules {
+    rules: Vec<Rule>,
+}
+
+impl IgnoreRules {
+    /// Rules plus those of `<root>/<dir>/.gitignore`, if it exists
+    pub fn extended(&self, root: &Path, dir: &str) -> Self {
+        let mut next = self.clone();
+        let Ok(text) = fs::read_to_string(root.join(dir).join(".gitignore")) else {
+            return next;
+        };
+        next.rules.extend(text.lines().filter_map(|line| Rule::parse(dir, line)));
+        next
+    }
+
+    /// Whether `rel` (relative to the project root, `/`-separated) is ignored
+    pub fn is_ignored(&self, rel: &str, is_dir: bool) -> bool {
+        let mut ignored = false;
+        for rule in &self.rules {
+            if rule.dir_only && !is_dir {
+                continue;
+            }
+            let local = if rule.base.is_empty() {
+                rel
+            } else {
+                match rel.strip_prefix(&rule.base).and_then(|r| r.strip_prefix('/')) {
+                    Some(local) => local,
+                    None => continue,
+                }
+            };
+            let hit = if rule.anchored {
+                glob_match(&rule.pattern, local)
+            } else {
+                glob_match(&rule.pattern, local.rsplit('/').next().unwrap_or(local))
+            };
+            if hit {
+                ignored = !rule.negated;
+            }
+        }
+        ignored
+    }
+}
+
+/// Match `text` against a glob where `*` and `?` stay within one path segment
+/// and `**` spans any number of segments
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let t: Vec<char> = text.chars().collect();
+    glob_at(&p, &t)
+}
+
+fn glob_at(p: &[char], t: &[char]) -> bool {
+    match p.first() {
+        None => t.is_empty(),
+        Some('*') if p.get(1) == Some(&'*') => {
+            // `**/` may also match zero segments
+            let rest = if p.get(2) == Some(&'/') { &p[3..] } else { &p[2..] };
+            (0..=t.len()).any(|i| glob_at(rest, &t[i..]))
+        }
+        Some('*') => (0..=t.len())
+            .take_while(|&i| i == 0 || t[i - 1] != '/')
+            .any(|i| glob_at(&p[1..], &t[i..])),
+        Some('?') => t.first().is_some_and(|&c| c != '/') && glob_at(&p[1..], &t[1..]),
+        Some(&c) => t.first() == Some(&c) && glob_at(&p[1..], &t[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{glob_match, IgnoreRules, Rule};
+
+    fn rules(lines: &[&str]) -> IgnoreRules {
+        IgnoreRules { rules: lines.iter().filter_map(|l| Rule::parse("", l)).collect() }
+    }
+
+    #[test]
+    fn follows_gitignore_matching() {
+        assert!(glob_match("*.log", "debug.log"));
+        assert!(!glob_match("src/*.rs", "src/a/b.rs"));
+        assert!(glob_match("src/**/*.rs", "src/b.rs"));
+        assert!(glob_match("src/**/*.rs", "src/a/b.rs"));
+
+        let ignore = rules(&["target/", "*.min.js", "!keep.min.js", "/build"]);
+        assert!(ignore.is_ignored("crates/x/target", true));
+        assert!(!ignore.is_ignored("target", false));
+        assert!(ignore.is_ignored("web/app.min.js", false));
+        assert!(!ignore.is_ignored("web/keep.min.js", false));
+        assert!(ignore.is_ignored("build", true));
+        assert!(!ignore.is_ignored("src/build", true));
+    }
+}
@@ -0,0 +1,127 @@
+use std::path::{Path, PathBuf};
+
+/// How a missing path was matched to an existing project file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathGuess {
+    /// A diff prefix (`a/`, `b/`, `./`) was left on the path
+    Prefix,
+    /// Same path, different letter case
+    Case,
+    /// The only project file with this file name
+    Basename,
+    /// The only path within a few edits of it
+    EditDistance,
+}
+
+/// Closest existing file for a path that does not exist, trying the cheap,
+/// unambiguous explanations first. `files` are project-relative paths.
+pub fn resolve_path(missing: &Path, files: &[PathBuf]) -> Option<(PathBuf, PathGuess)> {
+    let wanted = slash_lower(missing);
+
+    let stripped = ["a/", "b/", "./"].iter().find_map(|p| wanted.strip_prefix(p)).map(|s| s.trim_start_matches("./"));
+    if let Some(stripped) = stripped {
+        if let Some(hit) = unique(files, |f| slash_lower(f) == stripped) {
+            return Some((hit, PathGuess::Prefix));
+        }
+    }
+    if let Some(hit) = unique(files, |f| slash_lower(f) == wanted) {
+        return Some((hit, PathGuess::Case));
+    }
+    // one edit per 4 characters of the file stem, so short new names (`a.rs` next
+    // to `b.rs`) are never mistaken for typos; the best must be unique
+    let stem = missing.file_stem().map_or(0, |s| s.to_string_lossy().chars().count());
+    let budget = stem / 4;
+
+    // a typo beside its file beats a same-named file elsewhere in the project
+    let dir = missing.parent().map(slash_lower).unwrap_or_default();
+    let siblings = files.iter().filter(|f| f.parent().map(slash_lower).unwrap_or_default() == dir);
+    if let Some(hit) = closest(&wanted, siblings, budget) {
+        return Some((hit, PathGuess::EditDistance));
+    }
+    let name = missing.file_name().map(|n| n.to_string_lossy().to_lowercase());
+    if let Some(name) = name {
+        let same_name = |f: &PathBuf| f.file_name().is_some_and(|n| n.to_string_lossy().to_lowercase() == name);
+        if let Some(hit) = unique(files, same_name) {
+            return Some((hit, PathGuess::Basename));
+        }
+    }
+    closest(&wanted, files.iter(), budget).map(|hit| (hit, PathGuess::EditDistance))
+}
+
+/// The only file within `budget` edits of `wanted` at the smallest distance
+fn closest<'a>(wanted: &str, files: impl Iterator<Item = &'a PathBuf>, budget: usize) -> Option<PathBuf> {
+    if budget == 0 {
+        return None;
+    }
+    let mut best: Option<(usize, &PathBuf)> = None;
+    let mut tied = false;
+    for file in files {
+        let distance = strsim::levenshtein(wanted, &slash_lower(file));
+        if distance > budget {
+            continue;
+        }
+        match best {
+            Some((d, _)) if distance > d => {}
+            Some((d, _)) if distance == d => tied = true,
+            _ => {
+                best = Some((distance, file));
+                tied = false;
+            }
+        }
+    }
+    match best {
+        Some((_, file)) if !tied => Some(file.clone()),
+        _ => None,
+    }
+}
+
+fn unique(files: &[PathBuf], pred: impl Fn(&PathBuf) -> bool) -> Option<PathBuf> {
+    let mut hits = files.iter().filter(|f| pred(f));
+    match (hits.next(), hits.next()) {
+        (Some(hit), None) => Some(hit.clone()),
+        _ => None,
+    }
+}
+
+fn slash_lower(path: &Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy().to_lowercase())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{resolve_path, PathGuess};
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn resolves_prefix_case_basename_and_typos() {
+        let files: Vec<PathBuf> =
+            ["src/ui/panel.rs", "src/ui/mod.rs", "src/core/mod.rs", "README.md"].iter().map(PathBuf::from).collect();
+        let guess = |p: &str| resolve_path(Path::new(p), &files);
+
+        assert_eq!(guess("b/src/ui/panel.rs"), Some((PathBuf::from("src/ui/panel.rs"), PathGuess::Prefix)));
+        assert_eq!(guess("readme.md"), Some((PathBuf::from("README.md"), PathGuess::Case)));
+        assert_eq!(guess("src/panel.rs"), Some((PathBuf::from("src/ui/panel.rs"), PathGuess::Basename)));
+        assert_eq!(guess("src/ui/panl.rs"), Some((PathBuf::from("src/ui/panel.rs"), PathGuess::EditDistance)));
+        // two mod.rs files, and nothing close to the rest
+        assert_eq!(guess("src/mod.rs"), None);
+        assert_eq!(guess("docs/guide.md"), None);
+        assert_eq!(guess("src/ui/tab.rs"), None);
+    }
+
+    #[test]
+    fn a_typo_beside_its_file_beats_a_same_named_file_elsewhere() {
+        let files: Vec<PathBuf> = ["src/ui/panel.rs", "src/legacy/panl.rs"].iter().map(PathBuf::from).collect();
+        assert_eq!(
+            resolve_path(Path::new("src/ui/panl.rs"), &files),
+            Some((PathBuf::from("src/ui/panel.rs"), PathGuess::EditDistance))
+        );
+        // with no near sibling the same-named file is still offered
+        assert_eq!(
+            resolve_path(Path::new("src/core/panl.rs"), &files),
+            Some((PathBuf::from("src/legacy/panl.rs"), PathGuess::Basename))
+        );
+    }
+}
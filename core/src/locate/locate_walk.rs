@@ -0,0 +1,76 @@
+use super::IgnoreRules;
+use crate::logger::Logger;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Bounds on how much of a project the cross-file search may read
+#[derive(Debug, Clone, Copy)]
+pub struct WalkLimits {
+    /// Files larger than this are skipped (generated or vendored code, data)
+    pub max_file_bytes: u64,
+    /// The walk stops after this many candidate files
+    pub max_files: usize,
+}
+
+impl Default for WalkLimits {
+    fn default() -> Self {
+        Self { max_file_bytes: 1_000_000, max_files: 5_000 }
+    }
+}
+
+/// Directories never searched, whatever `.gitignore` says
+fn always_skipped(name: &str) -> bool {
+    name == ".git" || name.starts_with(".applydiff_backup_")
+}
+
+/// Project files under `root` (relative paths, sorted per directory) that
+/// `.gitignore` rules keep and that fit the size limit.
+pub fn project_files(root: &Path, limits: WalkLimits, logger: &Logger) -> Vec<PathBuf> {
+    let mut out = Vec::new();
+    let mut pending = vec![(PathBuf::new(), IgnoreRules::default())];
+    while let Some((dir, inherited)) = pending.pop() {
+        let dir_key = slash_path(&dir);
+        let rules = inherited.extended(root, &dir_key);
+        let Ok(entries) = fs::read_dir(root.join(&dir)) else {
+            continue;
+        };
+        let mut entries: Vec<_> = entries.flatten().collect();
+        entries.sort_by_key(|e| e.file_name());
+
+        let mut subdirs = Vec::new();
+        for entry in entries {
+            let name = entry.file_name();
+            let Some(name) = name.to_str() else {
+                continue;
+            };
+            let Ok(kind) = entry.file_type() else {
+                continue;
+            };
+            let rel = dir.join(name);
+            let is_dir = kind.is_dir();
+            if (is_dir && always_skipped(name)) || rules.is_ignored(&slash_path(&rel), is_dir) {
+                continue;
+            }
+            if is_dir {
+                subdirs.push(rel);
+            } else if kind.is_file() && entry.metadata().is_ok_and(|m| m.len() <= limits.max_file_bytes) {
+                if out.len() == limits.max_files {
+                    logger.info(
+                        "locate",
+                        "walk_limit_reached",
+                        &format!("stopped after {} files; the rest of the project was not searched", limits.max_files),
+                    );
+                    return out;
+                }
+                out.push(rel);
+            }
+        }
+        // reversed so the stack visits subdirectories in name order
+        pending.extend(subdirs.into_iter().rev().map(|d| (d, rules.clone())));
+    }
+    out
+}
+
+fn slash_path(path: &Path) -> String {
+    path.components().map(|c| c.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/")
+}
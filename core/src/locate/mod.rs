@@ -0,0 +1,72 @@
+use crate::logger::Logger;
+use crate::parse::MatchMode;
+use crate::r#match::{MatchOptions, MatchPipeline};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+mod locate_ignore;
+mod locate_path;
+mod locate_walk;
+
+pub use locate_ignore::IgnoreRules;
+pub use locate_path::{resolve_path, PathGuess};
+pub use locate_walk::{project_files, WalkLimits};
+
+/// A block whose `from` was found in another file than the one it names
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Relocation {
+    /// Path as written in the patch
+    pub named: PathBuf,
+    /// The one project file that holds `from`
+    pub found: PathBuf,
+}
+
+/// Search the project for the single file other than `named` that contains
+/// `from`, using only the exact and normalized-equality tiers of `pipeline`.
+/// `None` when no file or more than one file holds it.
+pub fn find_elsewhere(
+    root: &Path,
+    named: &Path,
+    from: &str,
+    pipeline: &MatchPipeline,
+    limits: WalkLimits,
+    logger: &Logger,
+) -> Option<PathBuf> {
+    let opts = MatchOptions { mode: MatchMode::NoFuzzy, ..MatchOptions::default() };
+    let probe = probe_word(from);
+    let mut found: Option<PathBuf> = None;
+    for rel in project_files(root, limits, logger) {
+        if rel == named {
+            continue;
+        }
+        // unreadable or non-UTF-8 files can't hold the block
+        let Ok(text) = fs::read_to_string(root.join(&rel)) else {
+            continue;
+        };
+        if probe.is_some_and(|word| !text.contains(word)) || pipeline.find(&text, from, &opts, logger).is_none() {
+            continue;
+        }
+        if let Some(first) = &found {
+            logger.info(
+                "locate",
+                "relocation_ambiguous",
+                &format!("FROM found in both {} and {}", first.display(), rel.display()),
+            );
+            return None;
+        }
+        found = Some(rel);
+    }
+    match &found {
+        Some(rel) => logger.info("locate", "relocation_found", &format!("FROM found only in {}", rel.display())),
+        None => logger.info("locate", "relocation_not_found", "FROM found in no other project file"),
+    }
+    found
+}
+
+/// Longest identifier-like word of `from` (at least 4 chars): every file that
+/// can match through an equality tier contains it verbatim
+fn probe_word(from: &str) -> Option<&str> {
+    from.split(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .filter(|w| w.len() >= 4)
+        .max_by_key(|w| w.len())
+}
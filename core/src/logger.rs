@@ -1,13 +1,14 @@
 use chrono::Utc;
 use serde_json::json;
-use std::cell::RefCell;
-use std::rc::Rc;
+use std::sync::{Arc, Mutex};
 
+/// Thread-safe: parallel scoring and per-file previews share one logger.
+/// Each record is written whole, so lines from different threads never interleave.
 #[derive(Clone, Debug)]
 pub struct Logger {
     rid: u64,
     // Optional buffer for capturing logs during tests
-    output: Option<Rc<RefCell<String>>>,
+    output: Option<Arc<Mutex<String>>>,
 }
 
 impl Logger {
@@ -17,7 +18,7 @@ impl Logger {
     }
 
     /// Creates a new logger for testing that captures output to a string buffer.
-    pub fn new_for_test(rid: u64, buffer: Option<Rc<RefCell<String>>>) -> Self {
+    pub fn new_for_test(rid: u64, buffer: Option<Arc<Mutex<String>>>) -> Self {
         Self { rid, output: buffer }
     }
 
@@ -46,8 +47,9 @@ impl Logger {
         
         // If an output buffer is configured (for testing), write to it.
         // Otherwise, print to standard output for live runs.
-        if let Some(output_rc) = &self.output {
-            let mut writer = output_rc.borrow_mut();
+        if let Some(output) = &self.output {
+            // a panic elsewhere while holding the lock leaves the buffer usable
+            let mut writer = output.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
             writer.push_str(&rec.to_string());
             writer.push('\n');
         } else {
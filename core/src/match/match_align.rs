@@ -0,0 +1,161 @@
+use super::{normalize_newlines, normalize_ws_preserve_newlines, trim_eol, MatchContext, MatchResult, MatchStrategy, MatchTier};
+use similar::{capture_diff_slices, Algorithm, DiffOp};
+use std::collections::HashMap;
+
+/// Fewest unique lines shared by block and file before an alignment is trusted
+const MIN_ANCHORS: usize = 2;
+/// Largest region, as a multiple of the block's line count, an alignment may span
+const MAX_SPAN_FACTOR: usize = 3;
+
+/// (block line, file line) of a line unique to both
+type Anchor = (usize, usize);
+
+/// Tier 7: line-level alignment the way `git` does it: lines unique to both `from` and
+/// the file are aligned with patience diff, and the region is derived from the
+/// aligned anchors. Unlike the window tiers this tolerates any number of
+/// inserted or dropped lines, as long as the share of block lines found in the
+/// region still clears `min_score`. Lines only the file has are not penalized;
+/// the applier keeps them.
+pub struct PatienceAlignStrategy;
+
+impl MatchStrategy for PatienceAlignStrategy {
+    fn name(&self) -> &'static str { "patience-align" }
+    fn tier(&self) -> MatchTier { MatchTier::Aligned }
+    fn tolerates_edits(&self) -> bool { true }
+
+    fn find(&self, ctx: &MatchContext) -> Option<MatchResult> {
+        let needle = normalize_newlines(trim_eol(ctx.needle));
+        let needle_keys: Vec<String> = needle.lines().map(normalize_ws_preserve_newlines).collect();
+        let file_keys = ctx.index.ws_keys();
+        let n = needle_keys.len();
+        if n < MIN_ANCHORS {
+            return None;
+        }
+
+        let anchors = unique_anchors(&needle_keys, file_keys);
+        let (first, last) = match densest_run(&anchors, n) {
+            Ok(run) => run,
+            Err(reason) => {
+                ctx.logger.info("matcher", "align_rejected", reason);
+                return None;
+            }
+        };
+
+        // Extrapolate the block's unanchored head and tail lines around the anchors
+        let (nf, ff) = first;
+        let (nl, fl) = last;
+        let lo = ff.saturating_sub(nf);
+        let hi = (fl + (n - 1 - nl)).min(file_keys.len() - 1);
+
+        if hi + 1 - lo > MAX_SPAN_FACTOR * n {
+            ctx.logger.info("matcher", "align_rejected", &format!("region lines {}-{} too large for the block", lo + 1, hi + 1));
+            return None;
+        }
+
+        let score = needle_coverage(&needle_keys, &file_keys[lo..=hi]);
+        if score < ctx.min_score {
+            ctx.logger.info(
+                "matcher",
+                "align_below_threshold",
+                &format!("lines {}-{}, score={:.3} < min={:.3}", lo + 1, hi + 1, score, ctx.min_score),
+            );
+            return None;
+        }
+
+        let (start, end) = (ctx.ranges[lo].0, ctx.ranges[hi].1);
+        ctx.logger.info(
+            "matcher",
+            "patience_align_match",
+            &format!(
+                "start={}, end={}, score={:.3}, lines {}-{} for {} block line(s)",
+                start, end, score, lo + 1, hi + 1, n
+            ),
+        );
+        Some(MatchResult {
+            start,
+            end,
+            score,
+            tier: MatchTier::Aligned,
+            margin: None,
+            assumed: None,
+            notes: Vec::new(),
+        })
+    }
+}
+
+/// Anchors in patience (LIS) order
+fn unique_anchors(needle: &[String], file: &[String]) -> Vec<Anchor> {
+    let mut in_file: HashMap<&str, (usize, usize)> = HashMap::new();
+    for (i, key) in file.iter().enumerate() {
+        in_file.entry(key).and_modify(|e| e.1 += 1).or_insert((i, 1));
+    }
+    let mut in_needle: HashMap<&str, usize> = HashMap::new();
+    for key in needle {
+        *in_needle.entry(key).or_default() += 1;
+    }
+
+    let candidates: Vec<Anchor> = needle
+        .iter()
+        .enumerate()
+        .filter(|(_, k)| !k.trim().is_empty() && in_needle[k.as_str()] == 1)
+        .filter_map(|(i, k)| match in_file.get(k.as_str()) {
+            Some(&(f, 1)) => Some((i, f)),
+            _ => None,
+        })
+        .collect();
+
+    // All keys are unique, so patience diff over the file-ordered copy is exactly the LIS
+    let needle_side: Vec<&str> = candidates.iter().map(|&(i, _)| needle[i].as_str()).collect();
+    let mut by_file = candidates.clone();
+    by_file.sort_by_key(|&(_, f)| f);
+    let file_side: Vec<&str> = by_file.iter().map(|&(i, _)| needle[i].as_str()).collect();
+
+    let mut out = Vec::new();
+    for op in capture_diff_slices(Algorithm::Patience, &needle_side, &file_side) {
+        if let DiffOp::Equal { old_index, len, .. } = op {
+            out.extend_from_slice(&candidates[old_index..old_index + len]);
+        }
+    }
+    out
+}
+
+/// Split the anchor chain where the file jumps much further than the block does
+/// and keep the run with the most anchors; ties are ambiguous.
+fn densest_run(anchors: &[Anchor], n: usize) -> Result<(Anchor, Anchor), &'static str> {
+    let mut runs: Vec<&[Anchor]> = Vec::new();
+    let mut begin = 0;
+    for i in 1..=anchors.len() {
+        let split = i == anchors.len() || {
+            let (pn, pf) = anchors[i - 1];
+            let (cn, cf) = anchors[i];
+            cf - pf > (cn - pn) + n
+        };
+        if split {
+            runs.push(&anchors[begin..i]);
+            begin = i;
+        }
+    }
+
+    let best = runs.iter().map(|r| r.len()).max().unwrap_or(0);
+    if best < MIN_ANCHORS {
+        return Err("too few unique lines shared with the file");
+    }
+    let mut winners = runs.iter().filter(|r| r.len() == best);
+    let run = winners.next().ok_or("too few unique lines shared with the file")?;
+    if winners.next().is_some() {
+        return Err("anchors split into equally strong regions");
+    }
+    Ok((run[0], run[run.len() - 1]))
+}
+
+/// Share of `needle` lines that align with `region` lines under patience diff
+fn needle_coverage(needle: &[String], region: &[String]) -> f64 {
+    let equal: usize = capture_diff_slices(Algorithm::Patience, needle, region)
+        .iter()
+        .map(|op| match *op {
+            DiffOp::Equal { len, .. } => len,
+            _ => 0,
+        })
+        .sum();
+    equal as f64 / needle.len() as f64
+}
@@ -0,0 +1,102 @@
+use super::{
+    normalize_newlines, normalize_ws_preserve_newlines, trim_eol, AssumedSpan, MatchContext, MatchResult,
+    MatchStrategy, MatchTier,
+};
+use strsim::normalized_damerau_levenshtein;
+
+/// Blocks shorter than this have no middle worth tolerating
+const MIN_NEEDLE_LINES: usize = 5;
+/// Anchors need enough non-whitespace text to be meaningful (`}` alone is not)
+const MIN_ANCHOR_CHARS: usize = 10;
+/// Anchors grow line by line up to this size until they carry `MIN_ANCHOR_CHARS`
+const MAX_ANCHOR_LINES: usize = 3;
+
+/// Tier 8: unique head and tail anchors in order; the span between them is the
+/// match even when the model paraphrased the middle, so the span's score is
+/// reported but not held to the block's fuzz. The divergent middle is reported
+/// as `assumed` so previews can show what was taken on trust, and the applier
+/// refuses a `to` that edits it.
+pub struct AnchorSandwichStrategy;
+
+impl MatchStrategy for AnchorSandwichStrategy {
+    fn name(&self) -> &'static str { "anchor-sandwich" }
+    fn tier(&self) -> MatchTier { MatchTier::AnchorSandwich }
+    fn tolerates_edits(&self) -> bool { true }
+
+    fn find(&self, ctx: &MatchContext) -> Option<MatchResult> {
+        let needle = normalize_newlines(trim_eol(ctx.needle));
+        let needle_keys: Vec<String> = needle.lines().map(normalize_ws_preserve_newlines).collect();
+        let n = needle_keys.len();
+        if n < MIN_NEEDLE_LINES {
+            return None;
+        }
+
+        // Leave at least one line between the anchors
+        let limit = MAX_ANCHOR_LINES.min((n - 1) / 2);
+        let hk = (1..=limit).find(|&k| anchor_chars(&needle_keys[..k]) >= MIN_ANCHOR_CHARS)?;
+        let tk = (1..=limit).find(|&k| anchor_chars(&needle_keys[n - k..]) >= MIN_ANCHOR_CHARS)?;
+        let head = &needle_keys[..hk];
+        let tail = &needle_keys[n - tk..];
+
+        let file_keys = ctx.index.ws_keys();
+
+        let [h] = ctx.index.ws_windows(head)[..] else { return None };
+        let [t] = ctx.index.ws_windows(tail)[..] else { return None };
+
+        // In order, not overlapping, and within a bounded distance of the needle's length
+        if t < h + hk || t + tk - h > 2 * n {
+            ctx.logger.info("matcher", "anchor_out_of_bounds", &format!("head_line={}, tail_line={}", h + 1, t + 1));
+            return None;
+        }
+
+        let start = ctx.ranges[h].0;
+        let end = ctx.ranges[t + tk - 1].1;
+
+        // Grow both anchors over lines that still agree so only the divergent middle is assumed
+        let (mut head_len, mut tail_len) = (hk, tk);
+        while h + head_len < t && head_len + tail_len < n && file_keys[h + head_len] == needle_keys[head_len] {
+            head_len += 1;
+        }
+        while t + tk - tail_len > h + head_len
+            && head_len + tail_len < n
+            && file_keys[t + tk - tail_len - 1] == needle_keys[n - tail_len - 1]
+        {
+            tail_len += 1;
+        }
+        let (mid_first, mid_last) = (h + head_len, t + tk - tail_len);
+        let mid_start = ctx.ranges[mid_first].0;
+        let mid_end = if mid_last > mid_first { ctx.ranges[mid_last - 1].1 } else { mid_start };
+
+        let needle_middle: Vec<&str> = needle.lines().skip(head_len).take(n - head_len - tail_len).collect();
+        let assumed = AssumedSpan {
+            first_line: mid_first + 1,
+            last_line: mid_last,
+            patch_first_line: head_len + 1,
+            file_text: ctx.haystack[mid_start..mid_end].to_string(),
+            patch_text: needle_middle.join("\n"),
+        };
+
+        let score = normalized_damerau_levenshtein(ctx.index.window(h, t + tk - h), &needle);
+        ctx.logger.info(
+            "matcher",
+            "anchor_sandwich_match",
+            &format!(
+                "start={}, end={}, score={:.3}, anchors={}+{} lines, assumed lines {}-{}",
+                start, end, score, hk, tk, assumed.first_line, assumed.last_line
+            ),
+        );
+        Some(MatchResult {
+            start,
+            end,
+            score,
+            tier: MatchTier::AnchorSandwich,
+            margin: None,
+            assumed: Some(assumed),
+            notes: Vec::new(),
+        })
+    }
+}
+
+fn anchor_chars(lines: &[String]) -> usize {
+    lines.iter().map(|l| l.chars().filter(|c| !c.is_whitespace()).count()).sum()
+}
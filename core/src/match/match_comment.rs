@@ -0,0 +1,52 @@
+use super::{code_lines, normalize_newlines, trim_eol, MatchContext, MatchResult, MatchStrategy, MatchTier};
+
+/// Tier 5 (opt-in), between the equality tiers and fuzzy: compares code with
+/// comments and blank lines removed (syntax chosen by file extension), so a
+/// `from` that dropped or reworded comments still lands. Inert unless the
+/// search carries a `CommentSyntax` (`ignore_comments` in `.applydiff.json`).
+pub struct CommentInsensitiveStrategy;
+
+impl MatchStrategy for CommentInsensitiveStrategy {
+    fn name(&self) -> &'static str { "comment-insensitive" }
+    fn tier(&self) -> MatchTier { MatchTier::CommentInsensitive }
+    fn tolerates_edits(&self) -> bool { true }
+
+    fn find(&self, ctx: &MatchContext) -> Option<MatchResult> {
+        let syntax = ctx.comments?;
+        let needle_keys = code_lines(&normalize_newlines(trim_eol(ctx.needle)), syntax);
+        let needle_code: Vec<&str> = needle_keys.iter().map(String::as_str).filter(|k| !k.is_empty()).collect();
+        if needle_code.is_empty() {
+            return None;
+        }
+
+        // (file line, key) of every line that still has code
+        let file_keys = ctx.index.code_keys(syntax);
+        let file_code: Vec<(usize, &str)> =
+            file_keys.iter().enumerate().filter(|(_, k)| !k.is_empty()).map(|(i, k)| (i, k.as_str())).collect();
+        let m = needle_code.len();
+        if file_code.len() < m {
+            return None;
+        }
+
+        let hits: Vec<(usize, usize)> = (0..=file_code.len() - m)
+            .filter(|&i| file_code[i..i + m].iter().map(|&(_, k)| k).eq(needle_code.iter().copied()))
+            .map(|i| (ctx.ranges[file_code[i].0].0, ctx.ranges[file_code[i + m - 1].0].1))
+            .collect();
+        let (start, end) = ctx.pick_nearest(&hits)?;
+
+        let region_lines = ctx.line_of(end.saturating_sub(1)) + 1 - ctx.line_of(start);
+        let note = format!(
+            "comments or blank lines differed: {} non-code line(s) in the file, {} in the patch",
+            region_lines - m,
+            needle_keys.len() - m
+        );
+        ctx.logger.info(
+            "matcher",
+            "comment_insensitive_match",
+            &format!("start={}, end={}, {}", start, end, note),
+        );
+        let mut result = MatchResult::exact(start, end).with_tier(MatchTier::CommentInsensitive);
+        result.notes.push(note);
+        Some(result)
+    }
+}
@@ -0,0 +1,84 @@
+use super::{
+    confusables_in, indent_width, normalize_confusables, normalize_newlines, normalize_ws_preserve_newlines, trim_eol,
+    MatchContext, MatchResult, MatchStrategy, MatchTier,
+};
+
+/// Tier 2: whitespace-normalized equality
+pub struct WhitespaceStrategy;
+
+impl MatchStrategy for WhitespaceStrategy {
+    fn name(&self) -> &'static str { "whitespace" }
+    fn tier(&self) -> MatchTier { MatchTier::Whitespace }
+
+    fn find(&self, ctx: &MatchContext) -> Option<MatchResult> {
+        let needle_ws = needle_lines(ctx).iter().map(|l| normalize_ws_preserve_newlines(l)).collect::<Vec<_>>();
+        let matches = windows_at(ctx, ctx.index.ws_windows(&needle_ws), needle_ws.len());
+        let (start, end) = ctx.pick_nearest(&matches)?;
+        ctx.logger.info("matcher", "normalized_ws_match", &format!("start={}, end={}", start, end));
+        Some(MatchResult::exact(start, end).with_tier(MatchTier::Whitespace))
+    }
+}
+
+/// Tier 3: relative-indentation-normalized equality
+pub struct RelativeIndentStrategy;
+
+impl MatchStrategy for RelativeIndentStrategy {
+    fn name(&self) -> &'static str { "relative-indent" }
+    fn tier(&self) -> MatchTier { MatchTier::RelativeIndent }
+
+    fn find(&self, ctx: &MatchContext) -> Option<MatchResult> {
+        let needle_rel: Vec<(Option<usize>, String)> = needle_lines(ctx)
+            .iter()
+            .map(|l| ((!l.trim().is_empty()).then(|| indent_width(l)), normalize_ws_preserve_newlines(l)))
+            .collect();
+        let matches = windows_at(ctx, ctx.index.relative_windows(&needle_rel), needle_rel.len());
+        let (start, end) = ctx.pick_nearest(&matches)?;
+        ctx.logger.info("matcher", "relative_indent_match", &format!("start={}, end={}", start, end));
+        Some(MatchResult::exact(start, end).with_tier(MatchTier::RelativeIndent))
+    }
+}
+
+/// Tier 4: Unicode-confusable-folded equality (smart quotes, NBSP, dashes, zero-width, full-width)
+pub struct ConfusableStrategy;
+
+impl MatchStrategy for ConfusableStrategy {
+    fn name(&self) -> &'static str { "confusable" }
+    fn tier(&self) -> MatchTier { MatchTier::Confusable }
+
+    fn find(&self, ctx: &MatchContext) -> Option<MatchResult> {
+        // Skipped when neither side has anything to fold: tier 1 already covered that case
+        let folded = confusables_in(ctx.needle);
+        if folded.is_empty() && ctx.haystack.is_ascii() {
+            return None;
+        }
+        let needle_conf: Vec<String> =
+            needle_lines(ctx).iter().map(|l| normalize_ws_preserve_newlines(&normalize_confusables(l))).collect();
+        let matches = windows_at(ctx, ctx.index.confusable_windows(&needle_conf), needle_conf.len());
+        let (start, end) = ctx.pick_nearest(&matches)?;
+
+        let mut chars = folded;
+        for ch in confusables_in(&ctx.haystack[start..end]) {
+            if !chars.contains(&ch) {
+                chars.push(ch);
+            }
+        }
+        let listed: Vec<String> = chars.iter().map(|c| format!("U+{:04X}", *c as u32)).collect();
+        ctx.logger.info(
+            "matcher",
+            "confusable_match",
+            &format!("start={}, end={}, folded=[{}]", start, end, listed.join(", ")),
+        );
+        Some(MatchResult::exact(start, end).with_tier(MatchTier::Confusable))
+    }
+}
+
+/// Lines of the needle as the equality tiers compare them: LF newlines, no trailing newline.
+/// A window can only be equal when it has exactly this many lines.
+fn needle_lines(ctx: &MatchContext) -> Vec<String> {
+    normalize_newlines(trim_eol(ctx.needle)).split('\n').map(String::from).collect()
+}
+
+/// Byte spans of `win`-line windows starting at the given lines
+fn windows_at(ctx: &MatchContext, starts: Vec<usize>, win: usize) -> Vec<(usize, usize)> {
+    starts.into_iter().map(|i| (ctx.ranges[i].0, ctx.ranges[i + win - 1].1)).collect()
+}
@@ -1,5 +1,25 @@
 use crate::logger::Logger;
-use super::MatchResult;
+use crate::parse::Occurrence;
+use super::{MatchContext, MatchResult, MatchStrategy, MatchTier};
+
+/// Tier 1: unique exact substring
+pub struct ExactStrategy;
+
+impl MatchStrategy for ExactStrategy {
+    fn name(&self) -> &'static str { "exact" }
+    fn tier(&self) -> MatchTier { MatchTier::Exact }
+
+    fn find(&self, ctx: &MatchContext) -> Option<MatchResult> {
+        try_exact_match(ctx.haystack, ctx.needle, ctx.logger).or_else(|| {
+            // Repeated snippet: only a proximity prior can choose between the copies
+            ctx.proximity?;
+            let hits: Vec<(usize, usize)> =
+                ctx.haystack.match_indices(ctx.needle).map(|(idx, _)| (idx, idx + ctx.needle.len())).collect();
+            let (start, end) = ctx.pick_nearest(&hits)?;
+            Some(MatchResult::exact(start, end))
+        })
+    }
+}
 
 /// Fast path: check for a UNIQUE exact substring
 pub fn try_exact_match(haystack: &str, needle: &str, logger: &Logger) -> Option<MatchResult> {
@@ -12,12 +32,50 @@ pub fn try_exact_match(haystack: &str, needle: &str, logger: &Logger) -> Option<
             "fast_path_match",
             &format!("unique exact substring (len={})", needle.len())
         );
-        return Some(MatchResult {
-            start: idx,
-            end: idx + needle.len(),
-            score: 1.0,
-        });
+        return Some(MatchResult::exact(idx, idx + needle.len()));
     }
     
     None
-}
\ No newline at end of file
+}
+
+/// Select explicitly requested exact occurrence(s) of `needle`.
+/// Repeated snippets are targeted deterministically, so there is no fuzzy fallback:
+/// an out-of-range selection yields `None`.
+pub fn find_exact_occurrences(
+    haystack: &str,
+    needle: &str,
+    occurrence: Occurrence,
+    logger: &Logger,
+) -> Option<Vec<MatchResult>> {
+    if occurrence == Occurrence::Unique {
+        return try_exact_match(haystack, needle, logger).map(|m| vec![m]);
+    }
+
+    let hits: Vec<MatchResult> = haystack
+        .match_indices(needle)
+        .map(|(idx, _)| MatchResult::exact(idx, idx + needle.len()))
+        .collect();
+    let total = hits.len();
+
+    let selected: Vec<MatchResult> = match occurrence {
+        Occurrence::Nth(n) => hits.into_iter().nth(n.saturating_sub(1)).into_iter().collect(),
+        Occurrence::Last => hits.into_iter().last().into_iter().collect(),
+        _ => hits,
+    };
+
+    if selected.is_empty() {
+        logger.info(
+            "matcher",
+            "occurrence_not_found",
+            &format!("requested {:?}, found {} exact occurrence(s)", occurrence, total),
+        );
+        return None;
+    }
+
+    logger.info(
+        "matcher",
+        "occurrence_match",
+        &format!("requested {:?}, selected {} of {} exact occurrence(s)", occurrence, selected.len(), total),
+    );
+    Some(selected)
+}
@@ -1,87 +1,221 @@
-use crate::logger::Logger;
-use super::{MatchResult, normalize_newlines, normalize_ws_preserve_newlines, normalize_relative_indent};
-use strsim::normalized_damerau_levenshtein;
-
-pub fn find_fuzzy_match(
-    haystack: &str,
-    needle: &str,
-    ranges: &[(usize, usize)],
-    win_min: usize,
-    win_max: usize,
-    min_score: f64,
-    logger: &Logger,
-) -> Option<MatchResult> {
-    // 1) Whitespace-normalized equality
-    let needle_ws = normalize_ws_preserve_newlines(needle);
-    let matches = scan_windows_equal(ranges, haystack, &needle_ws, win_min, win_max, |s| {
-        normalize_ws_preserve_newlines(s)
-    });
-    if matches.len() == 1 {
-        let (start, end) = matches[0];
-        logger.info("matcher", "normalized_ws_match", &format!("start={}, end={}", start, end));
-        return Some(MatchResult { start, end, score: 1.0 });
-    }
+use super::{
+    normalize_newlines, similarity, MatchContext, MatchResult, MatchStrategy, MatchTier, NearMiss, Shape, STRUCTURE_PENALTY,
+};
+use crate::parse::FuzzyMetric;
+use rayon::prelude::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Tier 6: window search scored by the block's metric (Damerau-Levenshtein by default), with ambiguity guard
+pub struct FuzzyStrategy;
+
+impl MatchStrategy for FuzzyStrategy {
+    fn name(&self) -> &'static str { "fuzzy" }
+    fn tier(&self) -> MatchTier { MatchTier::Fuzzy }
+    fn tolerates_edits(&self) -> bool { true }
 
-    // 2) Relative-indentation-normalized equality
-    let needle_rel = normalize_relative_indent(&normalize_ws_preserve_newlines(needle));
-    let matches = scan_windows_equal(ranges, haystack, &needle_rel, win_min, win_max, |s| {
-        normalize_relative_indent(&normalize_ws_preserve_newlines(s))
-    });
-    if matches.len() == 1 {
-        let (start, end) = matches[0];
-        logger.info("matcher", "relative_indent_match", &format!("start={}, end={}", start, end));
-        return Some(MatchResult { start, end, score: 1.0 });
+    fn find(&self, ctx: &MatchContext) -> Option<MatchResult> {
+        find_fuzzy_match(ctx)
     }
+}
+
+/// Best scores this far below `min_score` trigger a wider window search
+const NEAR_MISS: f64 = 0.15;
+/// Expansion may spend at most this multiple of the initial scan's work
+const EXPANSION_BUDGET_FACTOR: usize = 4;
+/// Smallest line radius expansion may reach, however short the block
+const MIN_EXPANSION_LINES: usize = 4;
+/// Initial scans with fewer windows than this stay on the calling thread
+const PARALLEL_MIN_WINDOWS: usize = 64;
 
-    // 3) Fuzzy match with Damerau-Levenshtein
+pub fn find_fuzzy_match(ctx: &MatchContext) -> Option<MatchResult> {
+    let MatchContext {
+        needle, ranges, index, win_min, win_max, min_score, margin, indent_scoped, parallel, metric, logger, ..
+    } = *ctx;
+
+    if metric != FuzzyMetric::default() {
+        logger.info("matcher", "fuzzy_metric", &format!("scoring windows with {}", metric));
+    }
     let needle_norm = normalize_newlines(needle);
+    let needle_shape = Shape::of(&needle_norm);
+    let penalized = AtomicUsize::new(0);
     let mut best_score: f64 = -1.0;
     let mut second_score: f64 = -1.0;
     let mut best_range: Option<(usize, usize)> = None;
+    // scoring work spent so far
+    let mut work = 0usize;
+    // windows above threshold, kept for proximity tie-breaking
+    let mut contenders: Vec<(f64, usize, usize)> = Vec::new();
 
-    for win in win_min..=win_max {
-        if win == 0 || ranges.len() < win { continue; }
-        
-        for i in 0..=ranges.len() - win {
-            let start = ranges[i].0;
-            let end = ranges[i + win - 1].1;
-            let slice_with_nl = &haystack[start..end];
-
-            // Trim trailing newline from slice
-            let mut slice = slice_with_nl;
-            if slice.ends_with('\n') {
-                slice = &slice[..slice.len() - 1];
-                if slice.ends_with('\r') {
-                    slice = &slice[..slice.len() - 1];
-                }
+    let score_window = |i: usize, win: usize, work: &mut usize| -> (f64, usize, usize) {
+        let start = ranges[i].0;
+        let end = ranges[i + win - 1].1;
+
+        // CRLF-insensitive scoring, without the window's trailing newline
+        let slice_norm = index.window(i, win);
+        *work += slice_norm.len() * needle_norm.len();
+        let mut score = similarity(metric, slice_norm, &needle_norm);
+
+        // Windows that cut through brackets or indentation blocks lose ground to ones that don't
+        if score >= min_score - NEAR_MISS {
+            let units = Shape::of(slice_norm).mismatches(&needle_shape, indent_scoped);
+            if units > 0 {
+                score -= STRUCTURE_PENALTY * units as f64;
+                penalized.fetch_add(1, Ordering::Relaxed);
             }
+        }
+        (score, start, end)
+    };
 
-            // CRLF-insensitive scoring
-            let slice_norm = normalize_newlines(slice);
-            let score = normalized_damerau_levenshtein(&slice_norm, &needle_norm);
+    // Score every window (in parallel when there are enough), then fold in scan
+    // order so the best, runner-up and tie-breaking match a sequential scan exactly
+    let windows: Vec<(usize, usize)> = (win_min..=win_max)
+        .filter(|&win| win > 0 && ranges.len() >= win)
+        .flat_map(|win| (0..=ranges.len() - win).map(move |i| (i, win)))
+        .collect();
+    let score_one = |&(i, win): &(usize, usize)| {
+        let mut spent = 0usize;
+        let (score, start, end) = score_window(i, win, &mut spent);
+        (score, start, end, spent)
+    };
+    let scored: Vec<(f64, usize, usize, usize)> = if parallel && windows.len() >= PARALLEL_MIN_WINDOWS {
+        logger.info(
+            "matcher",
+            "fuzzy_parallel",
+            &format!("{} windows on {} threads", windows.len(), rayon::current_num_threads()),
+        );
+        windows.par_iter().map(score_one).collect()
+    } else {
+        windows.iter().map(score_one).collect()
+    };
+
+    for &(score, start, end, spent) in &scored {
+        work += spent;
+        if score >= min_score {
+            contenders.push((score, start, end));
+        }
 
-            if score > best_score {
-                second_score = best_score;
-                best_score = score;
-                best_range = Some((start, end));
-            } else if score > second_score {
-                second_score = score;
+        if score > best_score {
+            second_score = best_score;
+            best_score = score;
+            best_range = Some((start, end));
+        } else if score > second_score {
+            second_score = score;
+        }
+    }
+
+    // Near miss: the block may have dropped or gained several lines. Grow the window
+    // range (doubling the radius) around every region that came within NEAR_MISS,
+    // within a bounded budget, so a second region the wider windows fit as well
+    // still counts as a runner-up.
+    let near_miss = best_score < min_score && best_score >= min_score - NEAR_MISS;
+    if near_miss {
+        let mut seeds = near_miss_regions(&windows, &scored, min_score - NEAR_MISS);
+        let n = win_max.saturating_sub(1).max(1);
+        let max_radius = n.max(MIN_EXPANSION_LINES);
+        let budget = work.saturating_mul(EXPANSION_BUDGET_FACTOR);
+        let mut spent = 0usize;
+        let (mut tried, mut radius) = (win_max - n, 2usize);
+        'expand: while tried < max_radius && best_score < min_score {
+            radius = radius.min(max_radius);
+            for seed in seeds.iter_mut() {
+                let sizes = (n.saturating_sub(radius)..n.saturating_sub(tried)).chain(n + tried + 1..=n + radius);
+                for win in sizes.filter(|&w| w > 0 && w <= ranges.len()) {
+                    let first = seed.at.saturating_sub(radius);
+                    let last = (seed.at + radius).min(ranges.len() - win);
+                    for i in first..=last {
+                        if spent > budget {
+                            logger.info("matcher", "window_budget_exhausted", &format!("radius={}, work={}", radius, spent));
+                            break 'expand;
+                        }
+                        let (score, start, end) = score_window(i, win, &mut spent);
+                        if score >= min_score {
+                            contenders.push((score, start, end));
+                        }
+                        if score > seed.score {
+                            (seed.score, seed.span, seed.next_at) = (score, (start, end), i);
+                        }
+                        if score > best_score {
+                            best_score = score;
+                            best_range = Some((start, end));
+                        }
+                    }
+                }
+            }
+            logger.info("matcher", "window_expanded", &format!("radius={}, best={:.3}", radius, best_score));
+            for seed in seeds.iter_mut() {
+                seed.at = seed.next_at;
+            }
+            tried = radius;
+            radius *= 2;
+        }
+        // the best of every other region is a runner-up; windows around the best overlap it
+        if let Some((best_start, best_end)) = best_range {
+            for seed in seeds.iter().filter(|s| s.span.1 <= best_start || s.span.0 >= best_end) {
+                second_score = second_score.max(seed.score);
             }
         }
     }
 
+    let penalized = penalized.into_inner();
+    if penalized > 0 {
+        logger.info(
+            "matcher",
+            "structure_penalty",
+            &format!("{} window(s) penalized for unbalanced brackets or cut indentation blocks", penalized),
+        );
+    }
+
     // Decide based on threshold and ambiguity
     if let Some((start, end)) = best_range {
         if best_score >= min_score {
             // Avoid wrong-place edits when two windows are nearly equal
-            if second_score >= 0.0 && (best_score - second_score) < 0.02 && second_score >= min_score {
-                logger.info("matcher", "ambiguous_match", &format!("best={:.3}, second={:.3}", best_score, second_score));
+            let gap = (second_score >= 0.0).then_some(best_score - second_score);
+            if gap.is_some_and(|g| g < margin) && second_score >= min_score {
+                if let Some((score, start, end)) = break_tie(ctx, contenders, best_score) {
+                    logger.info(
+                        "matcher",
+                        "fuzzy_match",
+                        &format!("start={}, end={}, score={:.3}, gap={:.3} (proximity)", start, end, score, best_score - second_score),
+                    );
+                    return Some(MatchResult {
+                        start,
+                        end,
+                        score,
+                        tier: MatchTier::Fuzzy,
+                        margin: gap,
+                        assumed: None,
+                        notes: Vec::new(),
+                    });
+                }
+                logger.info(
+                    "matcher",
+                    "ambiguous_match",
+                    &format!("best={:.3}, second={:.3}, margin={:.3}", best_score, second_score, margin),
+                );
+                record_near_miss(ctx, best_score, gap, (start, end), true);
                 return None;
             }
-            logger.info("matcher", "fuzzy_match", &format!("start={}, end={}, score={:.3}", start, end, best_score));
-            return Some(MatchResult { start, end, score: best_score });
+            logger.info(
+                "matcher",
+                "fuzzy_match",
+                &format!("start={}, end={}, score={:.3}, gap={:.3}", start, end, best_score, gap.unwrap_or(best_score)),
+            );
+            return Some(MatchResult {
+                start,
+                end,
+                score: best_score,
+                tier: MatchTier::Fuzzy,
+                margin: gap,
+                assumed: None,
+                notes: Vec::new(),
+            });
         } else {
             logger.info("matcher", "no_match_threshold", &format!("best={:.3} < min={:.3}", best_score, min_score));
+            // a lower threshold only helps if the runner-up would not then make it ambiguous
+            let gap = (second_score >= 0.0).then_some(best_score - second_score);
+            let floor = (best_score * 100.0).floor() / 100.0;
+            let ambiguous = gap.is_some_and(|g| g < margin) && second_score >= floor;
+            record_near_miss(ctx, best_score, gap, (start, end), ambiguous);
         }
     } else {
         logger.info("matcher", "no_candidates", "no windows produced a score");
@@ -90,27 +224,69 @@ pub fn find_fuzzy_match(
     None
 }
 
-fn scan_windows_equal(
-    ranges: &[(usize, usize)],
-    haystack: &str,
-    needle_xfm: &str,
-    win_min: usize,
-    win_max: usize,
-    mut xfm: impl FnMut(&str) -> String,
-) -> Vec<(usize, usize)> {
-    let mut hits = Vec::new();
-    if ranges.is_empty() { return hits; }
-    
-    for win in win_min..=win_max {
-        if win == 0 || ranges.len() < win { continue; }
-        for i in 0..=ranges.len() - win {
-            let start = ranges[i].0;
-            let end = ranges[i + win - 1].1;
-            let slice = &haystack[start..end];
-            if xfm(slice) == needle_xfm {
-                hits.push((start, end));
+/// A region expansion grows around: the first line of its best window so far
+/// (`at`, recentred to `next_at` after each round), that window's score and byte span
+struct Seed {
+    at: usize,
+    next_at: usize,
+    score: f64,
+    span: (usize, usize),
+}
+
+/// The best window of each region scoring at least `floor`, best first; a window
+/// sharing a line with a better one belongs to that one's region
+fn near_miss_regions(windows: &[(usize, usize)], scored: &[(f64, usize, usize, usize)], floor: f64) -> Vec<Seed> {
+    let mut candidates: Vec<(usize, &(usize, usize))> =
+        (0..windows.len()).filter(|&k| scored[k].0 >= floor).map(|k| (k, &windows[k])).collect();
+    // stable: equal scores stay in scan order, like the best window itself
+    candidates.sort_by(|a, b| scored[b.0].0.total_cmp(&scored[a.0].0));
+    let mut lines: Vec<(usize, usize)> = Vec::new();
+    let mut seeds = Vec::new();
+    for (k, &(i, win)) in candidates {
+        if lines.iter().all(|&(first, end)| i + win <= first || i >= end) {
+            lines.push((i, i + win));
+            let (score, start, end, _) = scored[k];
+            seeds.push(Seed { at: i, next_at: i, score, span: (start, end) });
+        }
+    }
+    seeds
+}
+
+fn record_near_miss(ctx: &MatchContext, score: f64, margin: Option<f64>, (start, end): (usize, usize), ambiguous: bool) {
+    let miss = NearMiss {
+        score,
+        margin,
+        first_line: ctx.line_of(start) + 1,
+        last_line: ctx.line_of(end.saturating_sub(1).max(start)) + 1,
+        ambiguous,
+    };
+    ctx.logger.info("matcher", "near_miss", &miss.to_string());
+    let _ = ctx.near_miss.set(miss);
+}
+
+/// Among windows within `margin` of the best, pick the region nearest the
+/// proximity prior. Overlapping windows are one region, represented by its best.
+fn break_tie(ctx: &MatchContext, mut contenders: Vec<(f64, usize, usize)>, best: f64) -> Option<(f64, usize, usize)> {
+    ctx.proximity?;
+    contenders.sort_by_key(|&(_, start, _)| start);
+    let mut regions: Vec<(f64, usize, usize)> = Vec::new();
+    let mut region_end = 0usize;
+    for c in contenders {
+        match regions.last_mut() {
+            Some(rep) if c.1 < region_end => {
+                region_end = region_end.max(c.2);
+                if c.0 > rep.0 {
+                    *rep = c;
+                }
+            }
+            _ => {
+                region_end = c.2;
+                regions.push(c);
             }
         }
     }
-    hits
-}
\ No newline at end of file
+    regions.retain(|&(score, _, _)| score >= best - ctx.margin);
+    let spans: Vec<(usize, usize)> = regions.iter().map(|&(_, start, end)| (start, end)).collect();
+    let (start, end) = ctx.pick_nearest(&spans)?;
+    regions.into_iter().find(|&(_, s, e)| (s, e) == (start, end))
+}
@@ -0,0 +1,219 @@
+use super::{code_lines, indent_width, line_ranges, normalize_confusables, normalize_ws_preserve_newlines, CommentSyntax};
+use std::borrow::Cow;
+use std::sync::OnceLock;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// Multiplier of the polynomial rolling hash over per-line hashes
+const HASH_BASE: u64 = 0x0100_0000_01b3;
+
+/// Per-haystack line data shared by every tier, built once instead of
+/// re-normalizing each window. The applier keeps one per file and reuses it
+/// for later blocks while the file's content is unchanged.
+pub struct LineIndex {
+    fingerprint: u64,
+    len: usize,
+    /// (start_byte, end_byte) of each line, newline included
+    ranges: Vec<(usize, usize)>,
+    /// The haystack with CRLF folded to LF; `lf_ranges[i]` is line `i` without its newline
+    text_lf: String,
+    lf_ranges: Vec<(usize, usize)>,
+    /// Whitespace-collapsed line text (tier 2 keys, also used by the alignment tiers)
+    ws: Vec<String>,
+    ws_hash: RollingHash,
+    /// Indentation width of each non-blank line; `None` for blank lines (tier 3)
+    indent: Vec<Option<usize>>,
+    /// Rolling hash of the line text after its indentation
+    rel_hash: RollingHash,
+    /// Tier 4 keys, built on first use
+    confusable: OnceLock<(Vec<String>, RollingHash)>,
+    /// Comment-stripped keys for the syntax first asked for
+    code: OnceLock<(CommentSyntax, Vec<String>)>,
+}
+
+impl LineIndex {
+    pub fn new(haystack: &str) -> Self {
+        let ranges = line_ranges(haystack);
+        let mut text_lf = String::with_capacity(haystack.len());
+        let mut lf_ranges = Vec::with_capacity(ranges.len());
+        let mut ws = Vec::with_capacity(ranges.len());
+        let mut indent = Vec::with_capacity(ranges.len());
+        for &(start, end) in &ranges {
+            let line = &haystack[start..end];
+            let body = line.strip_suffix('\n').map_or(line, |b| b.strip_suffix('\r').unwrap_or(b));
+            let at = text_lf.len();
+            text_lf.push_str(body);
+            lf_ranges.push((at, text_lf.len()));
+            text_lf.push('\n');
+            ws.push(normalize_ws_preserve_newlines(body));
+            indent.push((!body.trim().is_empty()).then(|| indent_width(body)));
+        }
+        let ws_hash = RollingHash::new(ws.iter().map(|k| hash_line(k)));
+        let rel_hash = RollingHash::new(ws.iter().zip(&indent).map(|(k, w)| hash_line(relative_key(k, *w))));
+        Self {
+            fingerprint: fingerprint(haystack),
+            len: haystack.len(),
+            ranges,
+            text_lf,
+            lf_ranges,
+            ws,
+            ws_hash,
+            indent,
+            rel_hash,
+            confusable: OnceLock::new(),
+            code: OnceLock::new(),
+        }
+    }
+
+    /// Whether this index was built from exactly `haystack`
+    pub fn is_for(&self, haystack: &str) -> bool {
+        self.len == haystack.len() && self.fingerprint == fingerprint(haystack)
+    }
+
+    pub fn ranges(&self) -> &[(usize, usize)] {
+        &self.ranges
+    }
+
+    /// Lines `i..i + win` with LF newlines and no trailing newline
+    pub fn window(&self, i: usize, win: usize) -> &str {
+        &self.text_lf[self.lf_ranges[i].0..self.lf_ranges[i + win - 1].1]
+    }
+
+    /// Whitespace-collapsed text of every line
+    pub fn ws_keys(&self) -> &[String] {
+        &self.ws
+    }
+
+    /// Start lines of windows equal to `needle` line by line after whitespace collapsing
+    pub fn ws_windows(&self, needle: &[String]) -> Vec<usize> {
+        self.ws_hash.candidates(needle.iter().map(|k| hash_line(k)), |i| self.ws[i..i + needle.len()] == *needle)
+    }
+
+    /// Start lines of windows equal to `needle` once common indentation is removed;
+    /// `needle` holds each line's indentation width (`None` if blank) and collapsed text.
+    pub fn relative_windows(&self, needle: &[(Option<usize>, String)]) -> Vec<usize> {
+        let needle_min = needle.iter().filter_map(|(w, _)| *w).min().unwrap_or(0);
+        let hashes = needle.iter().map(|(width, text)| hash_line(relative_key(text, *width)));
+        self.rel_hash.candidates(hashes, |i| {
+            let lines = i..i + needle.len();
+            let min = self.indent[lines.clone()].iter().flatten().min().copied().unwrap_or(0);
+            lines.zip(needle).all(|(j, (width, text))| match (self.indent[j], width) {
+                (Some(a), Some(b)) => a - min == b - needle_min && relative_text(&self.ws[j]) == relative_text(text),
+                (None, None) => true,
+                _ => false,
+            })
+        })
+    }
+
+    /// Start lines of windows equal to `needle` after confusable folding and whitespace collapsing
+    pub fn confusable_windows(&self, needle: &[String]) -> Vec<usize> {
+        let (keys, hash) = self.confusable.get_or_init(|| {
+            let keys: Vec<String> = (0..self.lf_ranges.len())
+                .map(|i| normalize_ws_preserve_newlines(&normalize_confusables(self.window(i, 1))))
+                .collect();
+            let hash = RollingHash::new(keys.iter().map(|k| hash_line(k)));
+            (keys, hash)
+        });
+        hash.candidates(needle.iter().map(|k| hash_line(k)), |i| keys[i..i + needle.len()] == *needle)
+    }
+
+    /// Comment-stripped key of every line (see `code_lines`)
+    pub fn code_keys(&self, syntax: CommentSyntax) -> Cow<'_, [String]> {
+        let (cached, keys) = self.code.get_or_init(|| (syntax, code_lines(&self.text_lf, syntax)));
+        if *cached == syntax {
+            Cow::Borrowed(keys)
+        } else {
+            Cow::Owned(code_lines(&self.text_lf, syntax))
+        }
+    }
+}
+
+/// Collapsed line text without its (already collapsed) indentation
+fn relative_text(ws_key: &str) -> &str {
+    ws_key.strip_prefix(' ').unwrap_or(ws_key)
+}
+
+/// Tier 3 key of a line: empty when blank, whatever whitespace it holds
+fn relative_key(ws_key: &str, width: Option<usize>) -> &str {
+    width.map_or("", |_| relative_text(ws_key))
+}
+
+fn hash_line(key: &str) -> u64 {
+    let mut h = DefaultHasher::new();
+    key.hash(&mut h);
+    h.finish()
+}
+
+fn fingerprint(text: &str) -> u64 {
+    hash_line(text)
+}
+
+/// Prefix hashes over line hashes, so any window's hash is O(1)
+struct RollingHash {
+    prefix: Vec<u64>,
+    pow: Vec<u64>,
+}
+
+impl RollingHash {
+    fn new(lines: impl Iterator<Item = u64>) -> Self {
+        let (mut prefix, mut pow) = (vec![0u64], vec![1u64]);
+        for h in lines {
+            prefix.push(prefix[prefix.len() - 1].wrapping_mul(HASH_BASE).wrapping_add(h));
+            pow.push(pow[pow.len() - 1].wrapping_mul(HASH_BASE));
+        }
+        Self { prefix, pow }
+    }
+
+    fn window(&self, i: usize, len: usize) -> u64 {
+        self.prefix[i + len].wrapping_sub(self.prefix[i].wrapping_mul(self.pow[len]))
+    }
+
+    /// Start lines whose window hash equals the needle's and that pass `verify`
+    fn candidates(&self, needle: impl Iterator<Item = u64>, verify: impl Fn(usize) -> bool) -> Vec<usize> {
+        let (mut target, mut m) = (0u64, 0usize);
+        for h in needle {
+            target = target.wrapping_mul(HASH_BASE).wrapping_add(h);
+            m += 1;
+        }
+        let n = self.prefix.len() - 1;
+        if m == 0 || m > n {
+            return Vec::new();
+        }
+        (0..=n - m).filter(|&i| self.window(i, m) == target && verify(i)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LineIndex;
+    use crate::r#match::{normalize_newlines, normalize_relative_indent_ws, normalize_ws_preserve_newlines, trim_eol};
+
+    #[test]
+    fn windows_agree_with_whole_text_normalization() {
+        let hay = "fn a() {\r\n\tif x  {\r\n\t\ty();\r\n\n\t}\r\n}\r\n  if x {\n      y();\n\n  }\n";
+        let index = LineIndex::new(hay);
+        let ranges = index.ranges().to_vec();
+        for win in 1..=4 {
+            for i in 0..=ranges.len() - win {
+                let slice = normalize_newlines(trim_eol(&hay[ranges[i].0..ranges[i + win - 1].1]));
+                assert_eq!(index.window(i, win), slice);
+
+                let ws: Vec<String> = normalize_ws_preserve_newlines(&slice).split('\n').map(String::from).collect();
+                assert!(index.ws_windows(&ws).contains(&i));
+
+                let rel: Vec<(Option<usize>, String)> = slice
+                    .split('\n')
+                    .map(|l| ((!l.trim().is_empty()).then(|| super::indent_width(l)), normalize_ws_preserve_newlines(l)))
+                    .collect();
+                let expected: Vec<usize> = (0..=ranges.len() - win)
+                    .filter(|&j| {
+                        let other = normalize_newlines(trim_eol(&hay[ranges[j].0..ranges[j + win - 1].1]));
+                        normalize_relative_indent_ws(&other) == normalize_relative_indent_ws(&slice)
+                    })
+                    .collect();
+                assert_eq!(index.relative_windows(&rel), expected);
+            }
+        }
+        assert!(index.is_for(hay) && !index.is_for("fn a() {}"));
+    }
+}
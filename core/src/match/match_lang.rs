@@ -0,0 +1,113 @@
+use super::normalize_ws_preserve_newlines;
+use std::path::Path;
+
+/// Comment markers of a language family
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommentSyntax {
+    pub line: &'static [&'static str],
+    pub block: Option<(&'static str, &'static str)>,
+}
+
+const C_LIKE: CommentSyntax = CommentSyntax { line: &["//"], block: Some(("/*", "*/")) };
+const CSS: CommentSyntax = CommentSyntax { line: &[], block: Some(("/*", "*/")) };
+const HASH: CommentSyntax = CommentSyntax { line: &["#"], block: None };
+const INI: CommentSyntax = CommentSyntax { line: &[";", "#"], block: None };
+const SQL: CommentSyntax = CommentSyntax { line: &["--"], block: Some(("/*", "*/")) };
+const LUA: CommentSyntax = CommentSyntax { line: &["--"], block: Some(("--[[", "]]")) };
+const HASKELL: CommentSyntax = CommentSyntax { line: &["--"], block: Some(("{-", "-}")) };
+const MARKUP: CommentSyntax = CommentSyntax { line: &[], block: Some(("<!--", "-->")) };
+
+/// Comment syntax for a file, chosen by extension (or well-known file name)
+pub fn comment_syntax(path: &Path) -> Option<CommentSyntax> {
+    let name = path.file_name()?.to_str()?;
+    if matches!(name, "Makefile" | "makefile" | "Dockerfile" | "CMakeLists.txt" | ".gitignore") {
+        return Some(HASH);
+    }
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    let syntax = match ext.as_str() {
+        "rs" | "c" | "h" | "cc" | "cpp" | "cxx" | "hpp" | "java" | "js" | "jsx" | "mjs" | "cjs" | "ts" | "tsx"
+        | "go" | "swift" | "kt" | "kts" | "cs" | "scala" | "dart" | "php" | "proto" | "zig" => C_LIKE,
+        "css" => CSS,
+        "scss" | "less" => C_LIKE,
+        "py" | "pyi" | "rb" | "sh" | "bash" | "zsh" | "fish" | "yml" | "yaml" | "toml" | "pl" | "r" | "tf"
+        | "cmake" | "mk" | "nix" | "ex" | "exs" | "cfg" | "conf" => HASH,
+        "ini" => INI,
+        "sql" => SQL,
+        "lua" => LUA,
+        "hs" => HASKELL,
+        "html" | "htm" | "xml" | "svg" | "vue" | "md" => MARKUP,
+        _ => return None,
+    };
+    Some(syntax)
+}
+
+/// Languages whose blocks are delimited by indentation rather than brackets
+pub fn indent_scoped(path: &Path) -> bool {
+    let ext = path.extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase);
+    matches!(ext.as_deref(), Some("py" | "pyi" | "yml" | "yaml"))
+}
+
+/// One key per line of `text`: the code with comments removed, indentation
+/// dropped and whitespace collapsed; empty for blank and comment-only lines.
+/// Block comments carry across lines; markers inside string literals are ignored.
+pub fn code_lines(text: &str, syntax: CommentSyntax) -> Vec<String> {
+    let mut in_block = false;
+    text.lines()
+        .map(|line| {
+            let mut code = String::with_capacity(line.len());
+            let mut quote: Option<char> = None;
+            let mut escaped = false;
+            let mut rest = line;
+            while let Some(c) = rest.chars().next() {
+                if in_block {
+                    let (_, close) = syntax.block.expect("in_block implies block syntax");
+                    match rest.find(close) {
+                        Some(at) => {
+                            rest = &rest[at + close.len()..];
+                            in_block = false;
+                            code.push(' ');
+                        }
+                        None => rest = "",
+                    }
+                    continue;
+                }
+                if let Some(q) = quote {
+                    if escaped {
+                        escaped = false;
+                    } else if c == '\\' {
+                        escaped = true;
+                    } else if c == q {
+                        quote = None;
+                    }
+                } else if let Some((open, _)) = syntax.block.filter(|(open, _)| rest.starts_with(open)) {
+                    rest = &rest[open.len()..];
+                    in_block = true;
+                    continue;
+                } else if syntax.line.iter().any(|m| rest.starts_with(m)) {
+                    break;
+                } else if c == '"' || c == '`' || (c == '\'' && rest[1..].contains('\'')) {
+                    quote = Some(c);
+                }
+                code.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+            normalize_ws_preserve_newlines(code.trim())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{code_lines, comment_syntax};
+    use std::path::Path;
+
+    #[test]
+    fn strips_line_block_and_trailing_comments() {
+        let rs = comment_syntax(Path::new("src/lib.rs")).unwrap();
+        let text = "let url = \"http://x\"; // fetch\n/* a\n   b */ let y = 2;\n\n// gone";
+        assert_eq!(code_lines(text, rs), ["let url = \"http://x\";", "", "let y = 2;", "", ""]);
+
+        let py = comment_syntax(Path::new("app.py")).unwrap();
+        assert_eq!(code_lines("x = '#1'  # tag", py), ["x = '#1'"]);
+    }
+}
@@ -0,0 +1,89 @@
+use crate::parse::FuzzyMetric;
+use similar::TextDiff;
+use std::collections::HashSet;
+use strsim::normalized_damerau_levenshtein;
+
+/// Share of the `weighted` score taken from identifiers and numbers alone;
+/// the rest is plain Damerau-Levenshtein, so whitespace and punctuation count 30%
+const WORD_WEIGHT: f64 = 0.7;
+
+/// Similarity of `a` and `b` in `0.0..=1.0` under `metric` (1.0 = identical).
+/// Both sides are expected with LF newlines.
+pub fn similarity(metric: FuzzyMetric, a: &str, b: &str) -> f64 {
+    match metric {
+        FuzzyMetric::DamerauLevenshtein => normalized_damerau_levenshtein(a, b),
+        FuzzyMetric::LineRatio => line_ratio(a, b),
+        FuzzyMetric::TokenJaccard => token_jaccard(a, b),
+        FuzzyMetric::Weighted => {
+            let words = normalized_damerau_levenshtein(&words(a), &words(b));
+            WORD_WEIGHT * words + (1.0 - WORD_WEIGHT) * normalized_damerau_levenshtein(a, b)
+        }
+    }
+}
+
+fn line_ratio(a: &str, b: &str) -> f64 {
+    let (a, b) = (a.trim_end_matches('\n'), b.trim_end_matches('\n'));
+    if a == b {
+        return 1.0;
+    }
+    f64::from(TextDiff::from_lines(a, b).ratio())
+}
+
+fn token_jaccard(a: &str, b: &str) -> f64 {
+    let (a, b): (HashSet<&str>, HashSet<&str>) = (tokens(a).collect(), tokens(b).collect());
+    let union = a.union(&b).count();
+    if union == 0 {
+        return 1.0;
+    }
+    a.intersection(&b).count() as f64 / union as f64
+}
+
+/// Identifier/number runs and single symbol characters; whitespace separates only
+fn tokens(text: &str) -> impl Iterator<Item = &str> {
+    let mut rest = text;
+    std::iter::from_fn(move || {
+        rest = rest.trim_start();
+        let first = rest.chars().next()?;
+        let len = if is_word(first) {
+            rest.find(|c: char| !is_word(c)).unwrap_or(rest.len())
+        } else {
+            first.len_utf8()
+        };
+        let (token, tail) = rest.split_at(len);
+        rest = tail;
+        Some(token)
+    })
+}
+
+/// Identifier and number runs of `text`, one space apart
+fn words(text: &str) -> String {
+    tokens(text).filter(|t| t.starts_with(is_word)).collect::<Vec<_>>().join(" ")
+}
+
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{similarity, tokens};
+    use crate::parse::FuzzyMetric::*;
+
+    #[test]
+    fn metrics_weigh_edits_differently() {
+        assert_eq!(tokens("let x=f(a_1, 2);").collect::<Vec<_>>(), ["let", "x", "=", "f", "(", "a_1", ",", "2", ")", ";"]);
+        for metric in [DamerauLevenshtein, LineRatio, TokenJaccard, Weighted] {
+            assert_eq!(similarity(metric, "a\nb", "a\nb"), 1.0);
+        }
+        assert_eq!(similarity(LineRatio, "a\nb\n", "a\nb"), 1.0);
+
+        // one of four lines rewritten: the line ratio only sees the line
+        let (a, b) = ("one\ntwo\nthree\nfour", "one\ntwo\nsomething else entirely\nfour");
+        assert!((similarity(LineRatio, a, b) - 0.75).abs() < 1e-6);
+        // reordered statements keep every token
+        assert_eq!(similarity(TokenJaccard, "a = 1;\nb = 2;", "b = 2;\na = 1;"), 1.0);
+        // punctuation and spacing changes cost less under the weighted metric
+        let (a, b) = ("call(x, y);", "call( x,y )");
+        assert!(similarity(Weighted, a, b) > similarity(DamerauLevenshtein, a, b));
+    }
+}
@@ -114,4 +114,89 @@ pub fn normalize_relative_indent(s: &str) -> String {
         out.push_str(nl);
     }
     out
-}
\ No newline at end of file
+}
+/// Column width of a line's leading indentation, expanding tabs to 4-column stops
+pub fn indent_width(line: &str) -> usize {
+    line.chars()
+        .take_while(|c| *c == ' ' || *c == '\t')
+        .fold(0, |col, c| if c == '\t' { (col / 4 + 1) * 4 } else { col + 1 })
+}
+
+/// Tier-3 comparison form: leading tabs expanded to 4 columns, common indentation
+/// removed, interior whitespace collapsed. Nesting survives, base indentation does not.
+pub fn normalize_relative_indent_ws(s: &str) -> String {
+    let min_cols = s
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(indent_width)
+        .min()
+        .unwrap_or(0);
+
+    let mut out = String::with_capacity(s.len());
+    for line in s.split_inclusive('\n') {
+        let (body, nl) = if let Some(stripped) = line.strip_suffix('\n') {
+            (stripped, "\n")
+        } else {
+            (line, "")
+        };
+        if !body.trim().is_empty() {
+            out.push_str(&" ".repeat(indent_width(body) - min_cols));
+            out.push_str(&normalize_ws_preserve_newlines(body.trim_start_matches([' ', '\t'])));
+        }
+        out.push_str(nl);
+    }
+    out
+}
+
+/// Fold Unicode confusables that chat UIs and models substitute: NFKC first
+/// (full-width forms, ligatures, compatibility spaces), then smart quotes,
+/// dashes and exotic spaces to ASCII; zero-width characters are dropped.
+pub fn normalize_confusables(s: &str) -> String {
+    use unicode_normalization::UnicodeNormalization;
+
+    if s.is_ascii() {
+        return s.to_string();
+    }
+    let mut out = String::with_capacity(s.len());
+    for ch in s.nfkc() {
+        match fold_confusable(ch) {
+            Some(folded) => out.push_str(folded),
+            None => out.push(ch),
+        }
+    }
+    out
+}
+
+/// Distinct characters in `s` that `normalize_confusables` would change
+pub fn confusables_in(s: &str) -> Vec<char> {
+    let mut found: Vec<char> = Vec::new();
+    for ch in s.chars().filter(|c| !c.is_ascii()) {
+        if found.contains(&ch) {
+            continue;
+        }
+        let single = ch.to_string();
+        if normalize_confusables(&single) != single {
+            found.push(ch);
+        }
+    }
+    found
+}
+
+fn fold_confusable(ch: char) -> Option<&'static str> {
+    match ch {
+        '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{201B}' | '\u{2032}' => Some("'"),
+        '\u{201C}' | '\u{201D}' | '\u{201E}' | '\u{201F}' | '\u{2033}' => Some("\""),
+        '\u{2010}' | '\u{2011}' | '\u{2012}' | '\u{2013}' | '\u{2014}' | '\u{2015}' | '\u{2212}' => Some("-"),
+        '\u{00A0}' | '\u{2000}'..='\u{200A}' | '\u{202F}' | '\u{205F}' | '\u{3000}' => Some(" "),
+        '\u{200B}' | '\u{200C}' | '\u{200D}' | '\u{2060}' | '\u{FEFF}' => Some(""),
+        _ => None,
+    }
+}
+
+/// Strip a single trailing `\n` / `\r\n`
+pub fn trim_eol(s: &str) -> &str {
+    match s.strip_suffix('\n') {
+        Some(body) => body.strip_suffix('\r').unwrap_or(body),
+        None => s,
+    }
+}
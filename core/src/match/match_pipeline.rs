@@ -0,0 +1,443 @@
+use crate::config::DEFAULT_AMBIGUITY_MARGIN;
+use crate::logger::Logger;
+use crate::parse::{FuzzyMetric, MatchMode};
+use super::{
+    normalize_newlines, AnchorSandwichStrategy, CommentInsensitiveStrategy, CommentSyntax, ConfusableStrategy,
+    ExactStrategy, FuzzyStrategy, LineIndex, MatchResult, MatchTier, NearMiss, PatienceAlignStrategy,
+    RelativeIndentStrategy, WhitespaceStrategy,
+};
+use std::sync::OnceLock;
+
+/// Per-search knobs, resolved from block headers and project defaults
+#[derive(Debug, Clone, Copy)]
+pub struct MatchOptions {
+    /// Minimum fuzzy score (`fuzz`)
+    pub min_score: f64,
+    /// Minimum best-vs-runner-up gap for fuzzy matches (`margin`)
+    pub margin: f64,
+    pub mode: MatchMode,
+    /// Where the block is expected; only consulted to break ties
+    pub proximity: Option<Proximity>,
+    /// Comment syntax of the target file; enables the comment-insensitive tier
+    pub comments: Option<CommentSyntax>,
+    /// Target language scopes blocks by indentation (Python, YAML); tightens structural checks
+    pub indent_scoped: bool,
+    /// Score fuzzy windows on the rayon thread pool (results are identical either way)
+    pub parallel: bool,
+    /// How the fuzzy tier scores a window
+    pub metric: FuzzyMetric,
+}
+
+impl Default for MatchOptions {
+    fn default() -> Self {
+        Self {
+            min_score: 0.85,
+            margin: DEFAULT_AMBIGUITY_MARGIN,
+            mode: MatchMode::Default,
+            proximity: None,
+            comments: None,
+            indent_scoped: false,
+            parallel: true,
+            metric: FuzzyMetric::default(),
+        }
+    }
+}
+
+/// Proximity prior for candidates that are otherwise equally good
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Proximity {
+    /// 0-based line from a `Line:` / `line=` / `@@ -N` hint
+    Near(usize),
+    /// 0-based line where the previous block in the same file matched
+    After(usize),
+}
+
+impl Proximity {
+    /// Rank of a candidate starting at `line` (lower is better). With `After`,
+    /// candidates before the previous block rank behind every candidate after it.
+    pub fn distance(self, line: usize, total_lines: usize) -> usize {
+        match self {
+            Proximity::Near(at) => line.abs_diff(at),
+            Proximity::After(at) if line >= at => line - at,
+            Proximity::After(at) => total_lines + (at - line),
+        }
+    }
+
+    /// The same prior for a search that starts at file line `first_line`
+    pub fn relative_to(self, first_line: usize) -> Self {
+        match self {
+            Proximity::Near(at) => Proximity::Near(at.saturating_sub(first_line)),
+            Proximity::After(at) => Proximity::After(at.saturating_sub(first_line)),
+        }
+    }
+}
+
+/// Everything a tier needs to search one needle in one haystack
+pub struct MatchContext<'a> {
+    pub haystack: &'a str,
+    pub needle: &'a str,
+    /// (start_byte, end_byte) of each haystack line, newline included
+    pub ranges: &'a [(usize, usize)],
+    /// Normalized per-line data of `haystack`, shared by the tiers
+    pub index: &'a LineIndex,
+    /// Smallest and largest window (in lines) worth comparing against the needle
+    pub win_min: usize,
+    pub win_max: usize,
+    pub min_score: f64,
+    pub margin: f64,
+    pub proximity: Option<Proximity>,
+    pub comments: Option<CommentSyntax>,
+    pub indent_scoped: bool,
+    pub parallel: bool,
+    pub metric: FuzzyMetric,
+    pub logger: &'a Logger,
+    /// Set by the fuzzy tier when its best candidate was rejected
+    pub near_miss: OnceLock<NearMiss>,
+}
+
+impl MatchContext<'_> {
+    /// 0-based haystack line containing byte `offset`
+    pub fn line_of(&self, offset: usize) -> usize {
+        self.ranges.partition_point(|&(start, _)| start <= offset).saturating_sub(1)
+    }
+
+    /// The only candidate, or the one strictly nearest the proximity prior.
+    /// Without a prior, or when the nearest two are equally near, nothing is picked.
+    pub fn pick_nearest(&self, candidates: &[(usize, usize)]) -> Option<(usize, usize)> {
+        match candidates {
+            [] => None,
+            [only] => Some(*only),
+            _ => {
+                let prior = self.proximity?;
+                let mut ranked: Vec<(usize, (usize, usize))> = candidates
+                    .iter()
+                    .map(|&c| (prior.distance(self.line_of(c.0), self.ranges.len()), c))
+                    .collect();
+                ranked.sort_by_key(|&(d, c)| (d, c.0));
+                if ranked[0].0 == ranked[1].0 {
+                    self.logger.info(
+                        "matcher",
+                        "proximity_tie",
+                        &format!("{} candidates equally near {:?}", candidates.len(), prior),
+                    );
+                    return None;
+                }
+                let chosen = ranked[0].1;
+                self.logger.info(
+                    "matcher",
+                    "proximity_tiebreak",
+                    &format!(
+                        "{} candidates; chose line {} nearest {:?}",
+                        candidates.len(),
+                        self.line_of(chosen.0) + 1,
+                        prior
+                    ),
+                );
+                Some(chosen)
+            }
+        }
+    }
+}
+
+/// One matching tier. Returning `None` hands the search to the next tier.
+pub trait MatchStrategy {
+    /// Stable identifier used to reorder or remove tiers
+    fn name(&self) -> &'static str;
+    /// Tier reported in `MatchResult`
+    fn tier(&self) -> MatchTier;
+    /// True for tiers that accept text differing in more than whitespace/encoding;
+    /// these are skipped for `Match: no-fuzzy` blocks.
+    fn tolerates_edits(&self) -> bool { false }
+    fn find(&self, ctx: &MatchContext) -> Option<MatchResult>;
+}
+
+/// Ordered list of tiers; the first tier that finds a match wins.
+pub struct MatchPipeline {
+    strategies: Vec<Box<dyn MatchStrategy>>,
+}
+
+impl Default for MatchPipeline {
+    /// exact → whitespace → relative indent → confusable → comment-insensitive (opt-in)
+    /// → Damerau-Levenshtein → patience alignment → anchor sandwich
+    fn default() -> Self {
+        Self::empty()
+            .with(ExactStrategy)
+            .with(WhitespaceStrategy)
+            .with(RelativeIndentStrategy)
+            .with(ConfusableStrategy)
+            .with(CommentInsensitiveStrategy)
+            .with(FuzzyStrategy)
+            .with(PatienceAlignStrategy)
+            .with(AnchorSandwichStrategy)
+    }
+}
+
+impl MatchPipeline {
+    /// Pipeline with no tiers; add them with `with` / `insert_before`.
+    pub fn empty() -> Self {
+        Self { strategies: Vec::new() }
+    }
+
+    /// Append a tier at the end.
+    pub fn with(mut self, strategy: impl MatchStrategy + 'static) -> Self {
+        self.strategies.push(Box::new(strategy));
+        self
+    }
+
+    /// Insert a tier before the named one (appends if `name` is absent).
+    pub fn insert_before(mut self, name: &str, strategy: impl MatchStrategy + 'static) -> Self {
+        let idx = self.position(name).unwrap_or(self.strategies.len());
+        self.strategies.insert(idx, Box::new(strategy));
+        self
+    }
+
+    /// Remove the named tier.
+    pub fn without(mut self, name: &str) -> Self {
+        self.strategies.retain(|s| s.name() != name);
+        self
+    }
+
+    /// Reorder tiers to follow `names`; unnamed tiers keep their relative order at the end.
+    pub fn reordered(mut self, names: &[&str]) -> Self {
+        self.strategies.sort_by_key(|s| names.iter().position(|n| *n == s.name()).unwrap_or(names.len()));
+        self
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.strategies.iter().map(|s| s.name()).collect()
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.strategies.iter().position(|s| s.name() == name)
+    }
+
+    /// Run the tiers allowed by `mode` in order.
+    pub fn find(
+        &self,
+        haystack: &str,
+        needle: &str,
+        opts: &MatchOptions,
+        logger: &Logger,
+    ) -> Option<MatchResult> {
+        self.find_indexed(haystack, &LineIndex::new(haystack), needle, opts, logger)
+    }
+
+    /// `find` with a prebuilt index of `haystack` (reused across blocks on the same file).
+    pub fn find_indexed(
+        &self,
+        haystack: &str,
+        index: &LineIndex,
+        needle: &str,
+        opts: &MatchOptions,
+        logger: &Logger,
+    ) -> Option<MatchResult> {
+        self.find_explained(haystack, index, needle, opts, logger).ok()
+    }
+
+    /// `find_indexed`, returning on failure the fuzzy tier's best rejected candidate
+    /// (`None` when the fuzzy tier did not run or scored no window)
+    pub fn find_explained(
+        &self,
+        haystack: &str,
+        index: &LineIndex,
+        needle: &str,
+        opts: &MatchOptions,
+        logger: &Logger,
+    ) -> std::result::Result<MatchResult, Option<NearMiss>> {
+        if needle.is_empty() {
+            return Ok(MatchResult::exact(haystack.len(), haystack.len()));
+        }
+
+        debug_assert!(index.is_for(haystack), "line index built from another haystack");
+        let ranges = index.ranges();
+        if ranges.is_empty() {
+            logger.info("matcher", "empty_haystack", "no lines to search");
+            return Err(None);
+        }
+
+        // Calculate window sizes
+        let needle_lines_norm = normalize_newlines(needle);
+        let n_lines = count_lines(&needle_lines_norm).max(1);
+        let ctx = MatchContext {
+            haystack,
+            needle,
+            ranges,
+            index,
+            win_min: n_lines.saturating_sub(1),
+            win_max: n_lines + 1,
+            min_score: opts.min_score,
+            margin: opts.margin,
+            proximity: opts.proximity,
+            comments: opts.comments,
+            indent_scoped: opts.indent_scoped,
+            parallel: opts.parallel,
+            metric: opts.metric,
+            logger,
+            near_miss: OnceLock::new(),
+        };
+
+        let allowed = self.strategies.iter().filter(|s| match opts.mode {
+            MatchMode::Default => true,
+            MatchMode::ExactOnly => s.tier() == MatchTier::Exact,
+            MatchMode::NoFuzzy => !s.tolerates_edits(),
+        });
+
+        let mut searched = false;
+        for strategy in allowed {
+            if let Some(result) = strategy.find(&ctx) {
+                return Ok(result);
+            }
+            if !searched {
+                logger.info(
+                    "matcher",
+                    "search_start",
+                    &format!("no {} match; layered search (needle_len={})", strategy.name(), needle.len()),
+                );
+                searched = true;
+            }
+        }
+        Err(ctx.near_miss.into_inner())
+    }
+}
+
+fn count_lines(s: &str) -> usize {
+    if s.is_empty() { 0 } else { s.lines().count().max(1) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FirstLine;
+
+    impl MatchStrategy for FirstLine {
+        fn name(&self) -> &'static str { "first-line" }
+        fn tier(&self) -> MatchTier { MatchTier::Fuzzy }
+        fn find(&self, ctx: &MatchContext) -> Option<MatchResult> {
+            let (start, end) = ctx.ranges[0];
+            Some(MatchResult {
+                start,
+                end,
+                score: 0.5,
+                tier: self.tier(),
+                margin: None,
+                assumed: None,
+                notes: Vec::new(),
+            })
+        }
+    }
+
+    #[test]
+    fn default_order_matches_documented_tiers() {
+        assert_eq!(
+            MatchPipeline::default().names(),
+            [
+                "exact",
+                "whitespace",
+                "relative-indent",
+                "confusable",
+                "comment-insensitive",
+                "fuzzy",
+                "patience-align",
+                "anchor-sandwich"
+            ]
+        );
+        let p = MatchPipeline::default()
+            .without("confusable")
+            .without("comment-insensitive")
+            .without("patience-align")
+            .without("anchor-sandwich")
+            .reordered(&["fuzzy"]);
+        assert_eq!(p.names(), ["fuzzy", "exact", "whitespace", "relative-indent"]);
+    }
+
+    #[test]
+    fn modes_skip_tiers() {
+        let logger = Logger::new_for_test(1, None);
+        let hay = "a   =  1\nb = 2\n";
+        let p = MatchPipeline::default();
+        let opts = |mode| MatchOptions { min_score: 0.9, mode, ..MatchOptions::default() };
+        assert!(p.find(hay, "a = 1", &opts(MatchMode::ExactOnly), &logger).is_none());
+        let m = p.find(hay, "a = 1", &opts(MatchMode::NoFuzzy), &logger).unwrap();
+        assert_eq!(m.tier, MatchTier::Whitespace);
+    }
+
+    #[test]
+    fn custom_tier_runs_in_position() {
+        let logger = Logger::new_for_test(1, None);
+        let p = MatchPipeline::empty().with(FirstLine);
+        let m = p.find("x\ny\n", "zzz", &MatchOptions::default(), &logger).unwrap();
+        assert_eq!((m.start, m.end), (0, 2));
+    }
+
+    #[test]
+    fn parallel_fuzzy_scan_matches_serial() {
+        let logger = Logger::new_for_test(1, None);
+        let mut hay = String::new();
+        for i in 0..40 {
+            hay.push_str(&format!("fn handler_{}(req: Request) -> Response {{\n    route(req, \"/v1/{}\")\n}}\n", i, i * 37));
+        }
+        let p = MatchPipeline::default().without("patience-align").without("anchor-sandwich");
+        let typo = "fn handler_31(req: Request) -> Respons {\n    route(req, \"/v1/1147\")\n}";
+        let vague = "fn handler_3(req: Request) {\n    route(req)\n}";
+        let run = |needle, parallel| {
+            let opts = MatchOptions { min_score: 0.8, parallel, ..MatchOptions::default() };
+            p.find(&hay, needle, &opts, &logger).map(|m| (m.start, m.end, m.score, m.margin))
+        };
+        assert!(run(typo, true).is_some());
+        assert_eq!(run(typo, true), run(typo, false));
+        assert_eq!(run(vague, true), run(vague, false));
+    }
+
+    #[test]
+    fn rejected_fuzzy_candidate_suggests_threshold() {
+        let logger = Logger::new_for_test(1, None);
+        let p = MatchPipeline::empty().with(FuzzyStrategy);
+        let opts = MatchOptions { min_score: 0.99, ..MatchOptions::default() };
+        let hay = "fn load(path: &str) -> Config {\n    let text = read(path);\n    parse(&text)\n}\n";
+        let needle = "fn load(path: &str) -> Config {\n    let txt = read(path);\n    parse(&txt)\n}";
+        let miss = p.find_explained(hay, &LineIndex::new(hay), needle, &opts, &logger).err().flatten().unwrap();
+        assert_eq!((miss.first_line, miss.last_line), (1, 4));
+        assert_eq!(miss.suggested_fuzz(), Some((miss.score * 100.0).floor() / 100.0));
+        assert!(miss.to_string().contains("unambiguous"));
+
+        // the same function twice: no threshold picks one
+        let twice = format!("{}\n{}", hay, hay);
+        let miss = p.find_explained(&twice, &LineIndex::new(&twice), needle, &opts, &logger).err().flatten().unwrap();
+        assert!(miss.ambiguous && miss.suggested_fuzz().is_none());
+        assert!(miss.to_string().ends_with("lowering fuzz won't help: ambiguous"));
+    }
+
+    #[test]
+    fn widened_windows_still_see_a_second_region() {
+        let logger = Logger::new_for_test(1, None);
+        let p = MatchPipeline::empty().with(FuzzyStrategy);
+        let opts = MatchOptions { min_score: 0.85, ..MatchOptions::default() };
+        let region = "build:\n\tcargo build --workspace\n\n\ntest:\n\tcargo test --workspace\n\n\n\
+                      lint:\n\tcargo fmt --check\n\tcargo clippy --workspace -- -D warnings\n";
+        let needle = "build:\n\tcargo build --workspace\ntest:\n\tcargo test --workspace\n\
+                      lint:\n\tcargo fmt -- --check\n\tcargo clippy --workspace -- -D warnings";
+        let once = format!(".PHONY: all\n\n{}\n\nrelease:\n\tcargo build --release\n", region);
+        assert!(p.find(&once, needle, &opts, &logger).is_some());
+
+        // the same targets in a second Makefile section: only the widened windows fit either
+        let twice = format!("{}\n# ci\n{}", once, region);
+        let miss = p.find_explained(&twice, &LineIndex::new(&twice), needle, &opts, &logger).err().flatten().unwrap();
+        assert!(miss.ambiguous, "{}", miss);
+    }
+
+    #[test]
+    fn anchor_sandwich_reports_assumed_middle() {
+        let logger = Logger::new_for_test(1, None);
+        let hay = "fn setup() {\n    let config = load();\n    let a = 1;\n    let b = 2;\n    finish(config);\n}\n";
+        let needle = "fn setup() {\n    let config = load();\n    something_else();\n    finish(config);\n}";
+        // the paraphrased middle drags the span under the default fuzz; the unique anchors still place it
+        let m = MatchPipeline::default().find(hay, needle, &MatchOptions::default(), &logger).unwrap();
+        assert_eq!(m.tier, MatchTier::AnchorSandwich);
+        assert!(m.score < MatchOptions::default().min_score);
+        let assumed = m.assumed.unwrap();
+        assert_eq!((assumed.first_line, assumed.last_line), (3, 4));
+        assert_eq!(assumed.file_text, "    let a = 1;\n    let b = 2;\n");
+        assert!(MatchPipeline::default().find(hay, needle, &MatchOptions { mode: MatchMode::NoFuzzy, ..MatchOptions::default() }, &logger).is_none());
+    }
+}
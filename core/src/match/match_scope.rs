@@ -0,0 +1,127 @@
+use crate::logger::Logger;
+use super::{indent_width, line_ranges, normalize_ws_preserve_newlines, trim_eol};
+
+/// Locate the block a `Within:` anchor names (`impl Parser`, `def test_two`) and
+/// return its byte range: from the anchor line through the end of its brace or
+/// indentation block. The anchor must occur on exactly one line, as whole words.
+pub fn find_scope(haystack: &str, anchor: &str, logger: &Logger) -> Option<(usize, usize)> {
+    let anchor = normalize_ws_preserve_newlines(anchor.trim());
+    if anchor.is_empty() {
+        return None;
+    }
+    let ranges = line_ranges(haystack);
+    let lines: Vec<&str> = ranges.iter().map(|&(s, e)| trim_eol(&haystack[s..e])).collect();
+
+    let hits: Vec<usize> = (0..lines.len())
+        .filter(|&i| contains_words(&normalize_ws_preserve_newlines(lines[i]), &anchor))
+        .collect();
+    let [head] = hits[..] else {
+        logger.info(
+            "matcher",
+            if hits.is_empty() { "scope_not_found" } else { "scope_ambiguous" },
+            &format!("within='{}', {} line(s) match", anchor, hits.len()),
+        );
+        return None;
+    };
+
+    let last = brace_block_end(&lines, head).unwrap_or_else(|| indent_block_end(&lines, head));
+    logger.info(
+        "matcher",
+        "scope_located",
+        &format!("within='{}', lines {}-{}", anchor, head + 1, last + 1),
+    );
+    Some((ranges[head].0, ranges[last].1))
+}
+
+/// `needle` occurs in `line` without word characters glued to either end
+fn contains_words(line: &str, needle: &str) -> bool {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    line.match_indices(needle).any(|(at, _)| {
+        let before = line[..at].chars().next_back();
+        let after = line[at + needle.len()..].chars().next();
+        let glued_before = before.is_some_and(is_word) && needle.starts_with(is_word);
+        let glued_after = after.is_some_and(is_word) && needle.ends_with(is_word);
+        !glued_before && !glued_after
+    })
+}
+
+/// Closing line of a `{ … }` block left open by the anchor line (or opened on
+/// the next non-blank line, Allman style). Braces inside string literals are skipped.
+fn brace_block_end(lines: &[&str], head: usize) -> Option<usize> {
+    let leaves_open = lines[head].matches('{').count() > lines[head].matches('}').count();
+    let opener = if leaves_open {
+        head
+    } else {
+        let next = (head + 1..lines.len()).find(|&i| !lines[i].trim().is_empty())?;
+        lines[next].trim_start().starts_with('{').then_some(next)?
+    };
+
+    let mut depth = 0i64;
+    for (i, line) in lines.iter().enumerate().skip(head) {
+        let mut quote: Option<char> = None;
+        let mut escaped = false;
+        for (at, c) in line.char_indices() {
+            match quote {
+                Some(_) if escaped => escaped = false,
+                Some(_) if c == '\\' => escaped = true,
+                Some(q) if c == q => quote = None,
+                Some(_) => {}
+                None => match c {
+                    '"' | '`' => quote = Some(c),
+                    // a lone `'` is a Rust lifetime, not a char literal
+                    '\'' if line[at + 1..].contains('\'') => quote = Some(c),
+                    '{' => depth += 1,
+                    '}' => depth -= 1,
+                    _ => {}
+                },
+            }
+        }
+        if i >= opener && depth <= 0 {
+            return Some(i);
+        }
+    }
+    None
+}
+
+/// Last line indented deeper than the anchor line (blank lines inside count;
+/// trailing blank lines do not).
+fn indent_block_end(lines: &[&str], head: usize) -> usize {
+    let base = indent_width(lines[head]);
+    let mut last = head;
+    for (i, line) in lines.iter().enumerate().skip(head + 1) {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if indent_width(line) <= base {
+            break;
+        }
+        last = i;
+    }
+    last
+}
+
+#[cfg(test)]
+mod tests {
+    use super::find_scope;
+    use crate::logger::Logger;
+
+    #[test]
+    fn finds_indentation_and_brace_blocks() {
+        let logger = Logger::new_for_test(1, None);
+        let py = "def test_one(opts={}):\n    x = 1\n\ndef test_two():\n    x = 1\n\n    y = 2\nz = 3\n";
+        let (s, e) = find_scope(py, "def test_two", &logger).unwrap();
+        assert_eq!(&py[s..e], "def test_two():\n    x = 1\n\n    y = 2\n");
+
+        let rs = "impl Lexer {\n    fn a() {}\n}\nimpl<'a> Parser<'a>\n{\n    fn a() { \"}\" }\n}\n";
+        let (s, e) = find_scope(rs, "Parser<'a>", &logger).unwrap();
+        assert_eq!(&rs[s..e], "impl<'a> Parser<'a>\n{\n    fn a() { \"}\" }\n}\n");
+    }
+
+    #[test]
+    fn requires_a_unique_whole_word_anchor() {
+        let logger = Logger::new_for_test(1, None);
+        let py = "def test_two_more():\n    pass\n";
+        assert!(find_scope(py, "def test_two", &logger).is_none());
+        assert!(find_scope("fn a() {}\nfn a() {}\n", "fn a", &logger).is_none());
+    }
+}
@@ -0,0 +1,87 @@
+use super::indent_width;
+
+/// Score deducted from a fuzzy window per structural mismatch with `from`
+pub const STRUCTURE_PENALTY: f64 = 0.05;
+/// Bracket mismatches beyond this count are not penalized further
+const MAX_BRACKET_UNITS: usize = 3;
+
+/// Structural outline of a snippet: net `()`/`[]`/`{}` balance, and whether
+/// its first and last non-blank lines sit deeper than its shallowest line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Shape {
+    balance: [i64; 3],
+    first_nested: bool,
+    last_nested: bool,
+}
+
+impl Shape {
+    pub fn of(text: &str) -> Self {
+        let mut balance = [0i64; 3];
+        for line in text.lines() {
+            let mut quote: Option<char> = None;
+            let mut escaped = false;
+            for (at, c) in line.char_indices() {
+                match quote {
+                    Some(_) if escaped => escaped = false,
+                    Some(_) if c == '\\' => escaped = true,
+                    Some(q) if c == q => quote = None,
+                    Some(_) => {}
+                    None => match c {
+                        '"' | '`' => quote = Some(c),
+                        '\'' if line[at + 1..].contains('\'') => quote = Some(c),
+                        '(' => balance[0] += 1,
+                        ')' => balance[0] -= 1,
+                        '[' => balance[1] += 1,
+                        ']' => balance[1] -= 1,
+                        '{' => balance[2] += 1,
+                        '}' => balance[2] -= 1,
+                        _ => {}
+                    },
+                }
+            }
+        }
+
+        let indents: Vec<usize> = text.lines().filter(|l| !l.trim().is_empty()).map(indent_width).collect();
+        let base = indents.iter().copied().min().unwrap_or(0);
+        Self {
+            balance,
+            first_nested: indents.first().is_some_and(|&i| i > base),
+            last_nested: indents.last().is_some_and(|&i| i > base),
+        }
+    }
+
+    /// Number of structural differences between a candidate window and `from`.
+    /// Edge indentation only counts for indentation-scoped languages (Python, YAML),
+    /// where a window starting or ending inside a nested block cuts through it.
+    pub fn mismatches(&self, needle: &Shape, indent_scoped: bool) -> usize {
+        let brackets: usize = self
+            .balance
+            .iter()
+            .zip(needle.balance.iter())
+            .map(|(a, b)| a.abs_diff(*b) as usize)
+            .sum();
+        let mut units = brackets.min(MAX_BRACKET_UNITS);
+        if indent_scoped {
+            units += usize::from(self.first_nested != needle.first_nested);
+            units += usize::from(self.last_nested != needle.last_nested);
+        }
+        units
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Shape;
+
+    #[test]
+    fn counts_unbalanced_brackets_and_cut_blocks() {
+        let needle = Shape::of("fn a() {\n    b();\n}");
+        assert_eq!(Shape::of("fn a() {\n    c();\n}").mismatches(&needle, false), 0);
+        assert_eq!(Shape::of("    b();\n}\n}").mismatches(&needle, false), 2);
+        assert_eq!(Shape::of("let s = \"{\";").mismatches(&Shape::of("let s = 1;"), false), 0);
+
+        let py = Shape::of("def f():\n    return 1");
+        assert_eq!(Shape::of("    return 1\ndef g():").mismatches(&py, true), 2);
+        assert_eq!(Shape::of("    return 1\ndef g():").mismatches(&py, false), 0);
+    }
+}
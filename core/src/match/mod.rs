@@ -1,63 +1,157 @@
 use crate::logger::Logger;
 
+mod match_align;
+mod match_anchor;
+mod match_comment;
+mod match_equal;
 mod match_exact;
 mod match_fuzzy;
+mod match_index;
+mod match_lang;
+mod match_metric;
 mod match_normalize;
+mod match_pipeline;
+mod match_scope;
+mod match_structure;
 
-pub use match_exact::try_exact_match;
-pub use match_fuzzy::find_fuzzy_match;
-pub use match_normalize::{normalize_newlines, normalize_ws_preserve_newlines, normalize_relative_indent};
+pub use match_align::PatienceAlignStrategy;
+pub use match_anchor::AnchorSandwichStrategy;
+pub use match_comment::CommentInsensitiveStrategy;
+pub use match_equal::{ConfusableStrategy, RelativeIndentStrategy, WhitespaceStrategy};
+pub use match_exact::{find_exact_occurrences, try_exact_match, ExactStrategy};
+pub use match_fuzzy::{find_fuzzy_match, FuzzyStrategy};
+pub use match_index::LineIndex;
+pub use match_lang::{code_lines, comment_syntax, indent_scoped, CommentSyntax};
+pub use match_metric::similarity;
+pub use match_normalize::{
+    confusables_in, indent_width, line_ranges, normalize_confusables, normalize_newlines, normalize_relative_indent,
+    normalize_relative_indent_ws, normalize_ws_preserve_newlines, trim_eol,
+};
+pub use match_pipeline::{MatchContext, MatchOptions, MatchPipeline, MatchStrategy, Proximity};
+pub use match_scope::find_scope;
+pub use match_structure::{Shape, STRUCTURE_PENALTY};
+
+/// Which matching tier located a block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchTier {
+    Exact,
+    Whitespace,
+    RelativeIndent,
+    Confusable,
+    CommentInsensitive,
+    Fuzzy,
+    Aligned,
+    AnchorSandwich,
+}
+
+impl std::fmt::Display for MatchTier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            MatchTier::Exact => "exact",
+            MatchTier::Whitespace => "whitespace",
+            MatchTier::RelativeIndent => "relative-indent",
+            MatchTier::Confusable => "confusable",
+            MatchTier::CommentInsensitive => "comment-insensitive",
+            MatchTier::Fuzzy => "fuzzy",
+            MatchTier::Aligned => "patience-align",
+            MatchTier::AnchorSandwich => "anchor-sandwich",
+        })
+    }
+}
 
 /// Result of locating the best match of `needle` within `haystack`
 pub struct MatchResult {
     pub start: usize,
     pub end: usize,
     pub score: f64,
+    pub tier: MatchTier,
+    /// Gap between the best and runner-up fuzzy scores (`None` without a runner-up)
+    pub margin: Option<f64>,
+    /// File lines taken on trust between anchors (anchor-sandwich tier only)
+    pub assumed: Option<AssumedSpan>,
+    /// What the tier tolerated, for previews (e.g. comments that differed)
+    pub notes: Vec<String>,
+}
+
+/// Middle of an anchor-sandwich match where the file and the patch's `from` disagree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssumedSpan {
+    /// 1-based, inclusive; `last_line < first_line` when the file has no middle lines
+    pub first_line: usize,
+    pub last_line: usize,
+    /// 1-based line in `from` where `patch_text` starts
+    pub patch_first_line: usize,
+    pub file_text: String,
+    pub patch_text: String,
+}
+
+/// Best fuzzy candidate of a block that matched nothing, for threshold advice
+#[derive(Debug, Clone, PartialEq)]
+pub struct NearMiss {
+    pub score: f64,
+    /// Gap to the runner-up window (`None` without one)
+    pub margin: Option<f64>,
+    /// 1-based, inclusive lines of the candidate
+    pub first_line: usize,
+    pub last_line: usize,
+    /// The runner-up is within the ambiguity margin, so no threshold would accept it
+    pub ambiguous: bool,
+}
+
+/// Candidates scoring below this are not worth a retry, whatever the threshold
+const LOWEST_SUGGESTED_FUZZ: f64 = 0.5;
+
+impl NearMiss {
+    /// Highest two-decimal `fuzz` that accepts the candidate, if a retry can succeed
+    pub fn suggested_fuzz(&self) -> Option<f64> {
+        let fuzz = (self.score * 100.0).floor() / 100.0;
+        (!self.ambiguous && fuzz >= LOWEST_SUGGESTED_FUZZ).then_some(fuzz)
+    }
+}
+
+impl std::fmt::Display for NearMiss {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let lines = format!("lines {}-{}", self.first_line, self.last_line);
+        write!(f, "best fuzzy score {:.2} at {}", self.score, lines)?;
+        match self.margin {
+            Some(gap) => write!(f, ", {:.2} ahead of the runner-up", gap)?,
+            None => f.write_str(", no runner-up")?,
+        }
+        match self.suggested_fuzz() {
+            Some(fuzz) => write!(f, "; retry with fuzz={:.2} (candidate {}, unambiguous)", fuzz, lines),
+            None if self.ambiguous => f.write_str("; lowering fuzz won't help: ambiguous"),
+            None => f.write_str("; lowering fuzz won't help: no close candidate"),
+        }
+    }
 }
 
-/// Top-level matching strategy (layered):
+impl MatchResult {
+    /// Result of an equality tier: full score, no runner-up
+    pub fn exact(start: usize, end: usize) -> Self {
+        Self { start, end, score: 1.0, tier: MatchTier::Exact, margin: None, assumed: None, notes: Vec::new() }
+    }
+
+    pub fn with_tier(mut self, tier: MatchTier) -> Self {
+        self.tier = tier;
+        self
+    }
+}
+
+/// Top-level matching strategy (layered, see `MatchPipeline::default`):
 /// 1) Exact substring
 /// 2) Whitespace-normalized equality
 /// 3) Relative-indentation-normalized equality
-/// 4) Fuzzy window search with ambiguity guard
+/// 4) Unicode-confusable-folded equality
+/// 5) Comment- and blank-line-insensitive equality (opt-in)
+/// 6) Fuzzy window search with ambiguity guard
+/// 7) Patience alignment of lines unique to block and file
+/// 8) Anchor sandwich: unique head/tail anchors around a divergent middle
 pub fn find_best_match(
     haystack: &str,
     needle: &str,
     min_score: f64,
     logger: &Logger,
 ) -> Option<MatchResult> {
-    if needle.is_empty() {
-        return Some(MatchResult { start: haystack.len(), end: haystack.len(), score: 1.0 });
-    }
-
-    // Fast path: exact match
-    if let Some(result) = try_exact_match(haystack, needle, logger) {
-        return Some(result);
-    }
-
-    logger.info("matcher", "search_start", &format!("no exact match; layered search (needle_len={})", needle.len()));
-
-    // Prepare line ranges
-    let ranges = line_ranges(haystack);
-    if ranges.is_empty() {
-        logger.info("matcher", "empty_haystack", "no lines to search");
-        return None;
-    }
-
-    // Calculate window sizes
-    let needle_lines_norm = normalize_newlines(needle);
-    let n_lines = count_lines(&needle_lines_norm).max(1);
-    let win_min = n_lines.saturating_sub(1);
-    let win_max = n_lines + 1;
-
-    // Try fuzzy matching
-    find_fuzzy_match(haystack, needle, &ranges, win_min, win_max, min_score, logger)
-}
-
-fn line_ranges(s: &str) -> Vec<(usize, usize)> {
-    match_normalize::line_ranges(s)
+    let opts = MatchOptions { min_score, ..MatchOptions::default() };
+    MatchPipeline::default().find(haystack, needle, &opts, logger)
 }
-
-fn count_lines(s: &str) -> usize {
-    if s.is_empty() { 0 } else { s.lines().count().max(1) }
-}
\ No newline at end of file
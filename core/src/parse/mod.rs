@@ -1,23 +1,216 @@
 use crate::error::{ErrorCode, PatchError, Result};
+use serde::Deserialize;
 use std::path::PathBuf;
 
 mod parse_classic;
 mod parse_armored;
 pub mod parse_base64; // expose constants for caps
+mod parse_render;
 
 pub use parse_classic::parse_classic_block;
 pub use parse_armored::parse_armored_block;
 // Export the strict decoder; do **not** re-export the lossy one anymore.
-pub use parse_base64::decode_base64_checked;
+pub use parse_base64::{decode_base64_checked, encode_base64};
+pub use parse_render::{render_block, render_patch};
 
 const MAX_BLOCKS: usize = 1000;
 
+/// Fuzzy threshold of blocks that don't set `fuzz=` / `Fuzz:`
+pub const DEFAULT_FUZZ: f64 = 0.85;
+
+/// Which syntax a block was written in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Dialect {
+    /// `>>> file:` ... `<<<`
+    #[default]
+    Classic,
+    /// `-----BEGIN APPLYDIFF AFB-1-----` with base64 bodies
+    Armored,
+}
+
+/// Which exact occurrence(s) of `from` a block targets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Occurrence {
+    /// `from` must occur exactly once (falls through to fuzzy tiers otherwise)
+    #[default]
+    Unique,
+    /// 1-based index of the occurrence to replace (`occurrence=2`)
+    Nth(usize),
+    /// Last occurrence in the file (`occurrence=last`)
+    Last,
+    /// Every occurrence in the file (`all=true`)
+    All,
+}
+
+/// Which matching tiers a block allows (`Match:` / `match=`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MatchMode {
+    /// Every tier of the applier's pipeline
+    #[default]
+    Default,
+    /// Exact substring only (`exact-only`)
+    ExactOnly,
+    /// Exact and normalized-equality tiers; nothing that tolerates edits (`no-fuzzy`)
+    NoFuzzy,
+}
+
+/// How the fuzzy tier scores a window against `from` (`Metric:` / `metric=`,
+/// or `fuzzy_metric` in `.applydiff.json`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FuzzyMetric {
+    /// Character-level Damerau-Levenshtein over the whole window
+    #[default]
+    DamerauLevenshtein,
+    /// Share of identical lines (`similar` line diff ratio)
+    LineRatio,
+    /// Jaccard index of the sets of identifiers, numbers and symbols
+    TokenJaccard,
+    /// Damerau-Levenshtein with whitespace and punctuation down-weighted
+    Weighted,
+}
+
+impl std::fmt::Display for FuzzyMetric {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            FuzzyMetric::DamerauLevenshtein => "damerau-levenshtein",
+            FuzzyMetric::LineRatio => "line-ratio",
+            FuzzyMetric::TokenJaccard => "token-jaccard",
+            FuzzyMetric::Weighted => "weighted",
+        })
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct PatchBlock {
     pub file: PathBuf,
     pub from: String,
     pub to: String,
     pub fuzz: f64,
+    /// Ambiguity margin override; `None` uses the project default
+    pub margin: Option<f64>,
+    pub occurrence: Occurrence,
+    pub match_mode: MatchMode,
+    /// 1-based line where the block is expected (`Line:` / `line=` / `@@ -N`); breaks ties only
+    pub line_hint: Option<usize>,
+    /// Scope anchor (`Within:` / `within=`); the search is limited to the block it opens
+    pub within: Option<String>,
+    /// Create the file even when a similar path exists (`Create:` / `create=true`)
+    pub create: bool,
+    /// Fuzzy scoring override; `None` uses the project default
+    pub metric: Option<FuzzyMetric>,
+    /// Syntax the block was parsed from (canonical blocks are written back in it)
+    pub dialect: Dialect,
+}
+
+/// Parse an `occurrence=` / `Occurrence:` value: a 1-based index or `last`.
+pub(crate) fn parse_occurrence(value: &str, context: &str) -> Result<Occurrence> {
+    let v = value.trim();
+    if v.eq_ignore_ascii_case("last") {
+        return Ok(Occurrence::Last);
+    }
+    match v.parse::<usize>() {
+        Ok(n) if n >= 1 => Ok(Occurrence::Nth(n)),
+        _ => Err(PatchError::Parse {
+            code: ErrorCode::ParseFailed,
+            message: format!("Invalid occurrence '{}'; expected a 1-based index or 'last'", v),
+            context: context.to_string(),
+        }),
+    }
+}
+
+/// Parse a `line=` / `Line:` value: a 1-based line number.
+pub(crate) fn parse_line_hint(value: &str, context: &str) -> Result<usize> {
+    match value.trim().parse::<usize>() {
+        Ok(n) if n >= 1 => Ok(n),
+        _ => Err(PatchError::Parse {
+            code: ErrorCode::ParseFailed,
+            message: format!("Invalid line hint '{}'; expected a 1-based line number", value.trim()),
+            context: context.to_string(),
+        }),
+    }
+}
+
+/// Old-file start line of a unified-diff hunk header (`@@ -12,7 +12,8 @@`).
+pub(crate) fn parse_hunk_hint(line: &str) -> Option<usize> {
+    let rest = line.trim().strip_prefix("@@")?.trim_start().strip_prefix('-')?;
+    let digits: String = rest.chars().take_while(char::is_ascii_digit).collect();
+    digits.parse::<usize>().ok().filter(|n| *n >= 1)
+}
+
+/// Parse a `fuzz=` / `margin=` (`Fuzz:` / `Margin:`) value, a number from 0 to 1.
+pub(crate) fn parse_fraction(name: &str, value: &str, context: &str) -> Result<f64> {
+    match value.trim().parse::<f64>() {
+        Ok(v) if (0.0..=1.0).contains(&v) => Ok(v),
+        _ => Err(PatchError::Parse {
+            code: ErrorCode::ParseFailed,
+            message: format!("Invalid {} '{}'; expected a number from 0 to 1", name, value.trim()),
+            context: context.to_string(),
+        }),
+    }
+}
+
+/// Parse a `match=` / `Match:` value.
+pub(crate) fn parse_match_mode(value: &str, context: &str) -> Result<MatchMode> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "default" | "any" => Ok(MatchMode::Default),
+        "exact-only" | "exact" => Ok(MatchMode::ExactOnly),
+        "no-fuzzy" => Ok(MatchMode::NoFuzzy),
+        other => Err(PatchError::Parse {
+            code: ErrorCode::ParseFailed,
+            message: format!("Invalid match mode '{}'; expected exact-only, no-fuzzy or default", other),
+            context: context.to_string(),
+        }),
+    }
+}
+
+/// Parse a `metric=` / `Metric:` value.
+pub(crate) fn parse_metric(value: &str, context: &str) -> Result<FuzzyMetric> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "damerau-levenshtein" | "dl" => Ok(FuzzyMetric::DamerauLevenshtein),
+        "line-ratio" => Ok(FuzzyMetric::LineRatio),
+        "token-jaccard" => Ok(FuzzyMetric::TokenJaccard),
+        "weighted" => Ok(FuzzyMetric::Weighted),
+        other => Err(PatchError::Parse {
+            code: ErrorCode::ParseFailed,
+            message: format!(
+                "Invalid metric '{}'; expected damerau-levenshtein, line-ratio, token-jaccard or weighted",
+                other
+            ),
+            context: context.to_string(),
+        }),
+    }
+}
+
+/// Parse a boolean block option (`true/false`, `yes/no`, `1/0`).
+pub(crate) fn parse_flag(value: &str, context: &str) -> Result<bool> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "true" | "yes" | "1" => Ok(true),
+        "false" | "no" | "0" => Ok(false),
+        other => Err(PatchError::Parse {
+            code: ErrorCode::ParseFailed,
+            message: format!("Invalid boolean '{}'; expected true or false", other),
+            context: context.to_string(),
+        }),
+    }
+}
+
+/// Combine `occurrence=` and `all=` into a single target selection.
+pub(crate) fn resolve_occurrence(
+    occurrence: Option<Occurrence>,
+    all: bool,
+    context: &str,
+) -> Result<Occurrence> {
+    match (occurrence, all) {
+        (Some(_), true) => Err(PatchError::Parse {
+            code: ErrorCode::ParseFailed,
+            message: "'occurrence' and 'all=true' cannot be combined".to_string(),
+            context: context.to_string(),
+        }),
+        (_, true) => Ok(Occurrence::All),
+        (Some(o), false) => Ok(o),
+        (None, false) => Ok(Occurrence::Unique),
+    }
 }
 
 #[derive(Default)]
@@ -1,5 +1,8 @@
 use crate::error::{ErrorCode, PatchError, Result};
-use crate::parse::{PatchBlock, decode_base64_checked};
+use crate::parse::{
+    decode_base64_checked, parse_flag, parse_fraction, parse_hunk_hint, parse_line_hint, parse_match_mode, parse_metric,
+    parse_occurrence, resolve_occurrence, Dialect, MatchMode, PatchBlock, DEFAULT_FUZZ,
+};
 use crate::parse::parse_base64::MAX_BASE64_DECODED_DEFAULT;
 use std::path::PathBuf;
 
@@ -10,8 +13,16 @@ pub fn parse_armored_block(
     lines.next();
 
     let mut path: Option<String> = None;
-    let mut fuzz: f64 = 0.85;
+    let mut fuzz: f64 = DEFAULT_FUZZ;
+    let mut margin: Option<f64> = None;
     let mut encoding = String::from("base64");
+    let mut occurrence = None;
+    let mut all = false;
+    let mut match_mode = MatchMode::Default;
+    let mut line_hint = None;
+    let mut within = None;
+    let mut create = false;
+    let mut metric = None;
 
     // Read headers until "From:"
     while let Some((_, l)) = lines.peek().cloned() {
@@ -27,9 +38,27 @@ pub fn parse_armored_block(
         if let Some(rest) = t.strip_prefix("Path:") {
             path = Some(rest.trim().to_string());
         } else if let Some(rest) = t.strip_prefix("Fuzz:") {
-            fuzz = rest.trim().parse::<f64>().unwrap_or(0.85);
+            fuzz = parse_fraction("fuzz", rest, t)?;
+        } else if let Some(rest) = t.strip_prefix("Margin:") {
+            margin = Some(parse_fraction("margin", rest, t)?);
         } else if let Some(rest) = t.strip_prefix("Encoding:") {
             encoding = rest.trim().to_lowercase();
+        } else if let Some(rest) = t.strip_prefix("Occurrence:") {
+            occurrence = Some(parse_occurrence(rest, t)?);
+        } else if let Some(rest) = t.strip_prefix("All:") {
+            all = parse_flag(rest, t)?;
+        } else if let Some(rest) = t.strip_prefix("Match:") {
+            match_mode = parse_match_mode(rest, t)?;
+        } else if let Some(rest) = t.strip_prefix("Line:") {
+            line_hint = Some(parse_line_hint(rest, t)?);
+        } else if let Some(rest) = t.strip_prefix("Within:") {
+            within = Some(rest.trim().to_string()).filter(|w| !w.is_empty());
+        } else if let Some(rest) = t.strip_prefix("Create:") {
+            create = parse_flag(rest, t)?;
+        } else if let Some(rest) = t.strip_prefix("Metric:") {
+            metric = Some(parse_metric(rest, t)?);
+        } else if let Some(n) = parse_hunk_hint(t) {
+            line_hint = line_hint.or(Some(n));
         }
         lines.next();
     }
@@ -39,6 +68,7 @@ pub fn parse_armored_block(
         message: "Armored block missing 'Path:' header".to_string(),
         context: "".to_string(),
     })?;
+    let occurrence = resolve_occurrence(occurrence, all, &file)?;
 
     // Expect From:
     match lines.next() {
@@ -118,6 +148,14 @@ pub fn parse_armored_block(
         from,
         to,
         fuzz: fuzz.clamp(0.0, 1.0),
+        margin: margin.map(|m| m.clamp(0.0, 1.0)),
+        occurrence,
+        match_mode,
+        line_hint,
+        within,
+        create,
+        metric,
+        dialect: Dialect::Armored,
     })
 }
 
@@ -154,6 +154,23 @@ pub fn decode_base64_checked(s: &str, max_decoded_len: usize) -> Result<Vec<u8>>
     Ok(out)
 }
 
+/// Standard padded Base64 (the alphabet `decode_base64_checked` accepts), unwrapped.
+pub fn encode_base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let x = chunk.iter().enumerate().fold(0u32, |x, (i, &b)| x | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[((x >> (18 - 6 * i)) & 0x3F) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
 /// Legacy *lossy* decoder retained for compatibility (not re-exported).
 /// It ignores invalid bytes and treats any padding loosely.
 pub fn decode_base64_lossy(s: &str) -> Vec<u8> {
@@ -209,6 +226,15 @@ mod tests {
         assert_eq!(String::from_utf8(decoded).unwrap(), "Hello, World!");
     }
 
+    #[test]
+    fn encodes_what_it_decodes() {
+        assert_eq!(encode_base64(b"Hello, World!"), "SGVsbG8sIFdvcmxkIQ==");
+        for text in ["", "a", "ab", "abc", "línea\r\n"] {
+            let encoded = encode_base64(text.as_bytes());
+            assert_eq!(decode_base64_checked(&encoded, 1024).unwrap(), text.as_bytes());
+        }
+    }
+
     #[test]
     fn rejects_invalid_characters() {
         let bad = "abcd#efgh"; // '#' is not allowed
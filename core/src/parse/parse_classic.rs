@@ -1,5 +1,8 @@
 use crate::error::{ErrorCode, PatchError, Result};
-use crate::parse::PatchBlock;
+use crate::parse::{
+    parse_flag, parse_fraction, parse_hunk_hint, parse_line_hint, parse_match_mode, parse_metric, parse_occurrence,
+    resolve_occurrence, Dialect, MatchMode, PatchBlock, DEFAULT_FUZZ,
+};
 use regex::Regex;
 use std::path::PathBuf;
 
@@ -7,7 +10,7 @@ pub fn parse_classic_block(
     lines: &mut std::iter::Peekable<std::iter::Enumerate<std::str::Lines<'_>>>
 ) -> Result<PatchBlock> {
     let re_head = Regex::new(
-        r#"^>>>\s*file:\s*(?P<file>[^|]+?)(?:\s*\|\s*fuzz=(?P<fuzz>[0-9.]+))?\s*$"#
+        r#"^>>>\s*file:\s*(?P<file>[^|]+?)\s*(?P<opts>(?:\|[^|]*)*)$"#
     ).unwrap();
 
     // Header
@@ -19,15 +22,55 @@ pub fn parse_classic_block(
 
     let caps = re_head.captures(header).ok_or_else(|| PatchError::Parse {
         code: ErrorCode::ParseFailed,
-        message: "Invalid header; expected '>>> file: <path> [| fuzz=<0..1>]'".to_string(),
+        message: "Invalid header; expected '>>> file: <path> [| fuzz=<0..1>] [| margin=<0..1>] [| occurrence=<n|last>] [| all=true] [| match=<exact-only|no-fuzzy>] [| line=<n>] [| within=<anchor>] [| create=true] [| metric=<damerau-levenshtein|line-ratio|token-jaccard|weighted>]'".to_string(),
         context: header.to_string(),
     })?;
 
     let file = caps["file"].trim().to_string();
-    let fuzz = caps
-        .name("fuzz")
-        .map(|m| m.as_str().parse::<f64>().unwrap_or(0.85))
-        .unwrap_or(0.85);
+
+    // Header options: `| key=value` pairs; `mode=` is accepted and ignored, other unknown keys fail
+    let mut fuzz = DEFAULT_FUZZ;
+    let mut margin = None;
+    let mut occurrence = None;
+    let mut all = false;
+    let mut match_mode = MatchMode::Default;
+    let mut line_hint = None;
+    let mut within = None;
+    let mut create = false;
+    let mut metric = None;
+    for opt in caps["opts"].split('|').map(str::trim).filter(|o| !o.is_empty()) {
+        let (key, value) = opt.split_once('=').ok_or_else(|| PatchError::Parse {
+            code: ErrorCode::ParseFailed,
+            message: format!("Invalid header option '{}'; expected key=value", opt),
+            context: header.to_string(),
+        })?;
+        match key.trim().to_ascii_lowercase().as_str() {
+            "fuzz" => fuzz = parse_fraction("fuzz", value, header)?,
+            "margin" => margin = Some(parse_fraction("margin", value, header)?),
+            "occurrence" => occurrence = Some(parse_occurrence(value, header)?),
+            "all" => all = parse_flag(value, header)?,
+            "match" => match_mode = parse_match_mode(value, header)?,
+            "line" => line_hint = Some(parse_line_hint(value, header)?),
+            "within" => within = Some(value.trim().to_string()).filter(|w| !w.is_empty()),
+            "create" => create = parse_flag(value, header)?,
+            "metric" => metric = Some(parse_metric(value, header)?),
+            "mode" => {}
+            other => {
+                return Err(PatchError::Parse {
+                    code: ErrorCode::ParseFailed,
+                    message: format!("Unknown header option '{}'", other),
+                    context: header.to_string(),
+                })
+            }
+        }
+    }
+    let occurrence = resolve_occurrence(occurrence, all, header)?;
+
+    // Optional unified-diff hunk header as a line hint (`@@ -N,M +N,M @@`)
+    if let Some(n) = lines.peek().and_then(|(_, l)| parse_hunk_hint(l)) {
+        line_hint = line_hint.or(Some(n));
+        lines.next();
+    }
 
     // Expect --- from
     match lines.next() {
@@ -80,5 +123,91 @@ pub fn parse_classic_block(
         from,
         to,
         fuzz: fuzz.clamp(0.0, 1.0),
+        margin: margin.map(|m| m.clamp(0.0, 1.0)),
+        occurrence,
+        match_mode,
+        line_hint,
+        within,
+        create,
+        metric,
+        dialect: Dialect::Classic,
     })
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::{FuzzyMetric, MatchMode, Occurrence, Parser};
+
+    fn header(opts: &str) -> String {
+        format!(">>> file: a.txt{}\n--- from\nx\n--- to\ny\n<<<\n", opts)
+    }
+
+    #[test]
+    fn parses_occurrence_options() {
+        let out = Parser::new().parse(&header(" | fuzz=0.9 | occurrence=2")).unwrap();
+        assert_eq!(out[0].occurrence, Occurrence::Nth(2));
+        assert!((out[0].fuzz - 0.9).abs() < f64::EPSILON);
+
+        let out = Parser::new().parse(&header(" | margin=0.1 | occurrence=last")).unwrap();
+        assert_eq!(out[0].margin, Some(0.1));
+        assert_eq!(out[0].occurrence, Occurrence::Last);
+
+        let out = Parser::new().parse(&header(" | all=true")).unwrap();
+        assert_eq!(out[0].occurrence, Occurrence::All);
+
+        let out = Parser::new().parse(&header("")).unwrap();
+        assert_eq!(out[0].occurrence, Occurrence::Unique);
+    }
+
+    #[test]
+    fn rejects_malformed_values_and_unknown_options() {
+        for opts in [" | fuzz=abc", " | margin=2x", " | fuzz=8.5", " | fuzzz=0.9"] {
+            assert!(Parser::new().parse(&header(opts)).is_err(), "{}", opts);
+        }
+        assert!(Parser::new().parse(&header(" | mode=patch")).is_ok());
+    }
+
+    #[test]
+    fn parses_match_mode() {
+        let out = Parser::new().parse(&header(" | match=exact-only")).unwrap();
+        assert_eq!(out[0].match_mode, MatchMode::ExactOnly);
+        let out = Parser::new().parse(&header(" | match=no-fuzzy")).unwrap();
+        assert_eq!(out[0].match_mode, MatchMode::NoFuzzy);
+        assert!(Parser::new().parse(&header(" | match=sloppy")).is_err());
+    }
+
+    #[test]
+    fn parses_line_hints() {
+        let out = Parser::new().parse(&header(" | line=42")).unwrap();
+        assert_eq!(out[0].line_hint, Some(42));
+        let hunk = ">>> file: a.txt\n@@ -17,4 +17,5 @@ fn main\n--- from\nx\n--- to\ny\n<<<\n";
+        assert_eq!(Parser::new().parse(hunk).unwrap()[0].line_hint, Some(17));
+        assert!(Parser::new().parse(&header(" | line=0")).is_err());
+    }
+
+    #[test]
+    fn parses_within_anchor() {
+        let out = Parser::new().parse(&header(" | within=def test_two")).unwrap();
+        assert_eq!(out[0].within.as_deref(), Some("def test_two"));
+    }
+
+    #[test]
+    fn parses_create_flag() {
+        assert!(Parser::new().parse(&header(" | create=true")).unwrap()[0].create);
+        assert!(!Parser::new().parse(&header("")).unwrap()[0].create);
+    }
+
+    #[test]
+    fn parses_metric() {
+        let out = Parser::new().parse(&header(" | metric=token-jaccard")).unwrap();
+        assert_eq!(out[0].metric, Some(FuzzyMetric::TokenJaccard));
+        assert_eq!(Parser::new().parse(&header("")).unwrap()[0].metric, None);
+        assert!(Parser::new().parse(&header(" | metric=cosine")).is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_occurrence() {
+        assert!(Parser::new().parse(&header(" | occurrence=0")).is_err());
+        assert!(Parser::new().parse(&header(" | occurrence=2 | all=true")).is_err());
+    }
+}
@@ -0,0 +1,115 @@
+use crate::parse::{encode_base64, Dialect, MatchMode, Occurrence, PatchBlock, DEFAULT_FUZZ};
+
+/// Base64 line length of rendered AFB-1 bodies
+const BASE64_WRAP: usize = 76;
+
+/// Blocks rendered one after another, each in its own dialect
+pub fn render_patch(blocks: &[PatchBlock]) -> String {
+    blocks.iter().map(render_block).collect::<Vec<_>>().join("\n")
+}
+
+/// Text that parses back to `blk`. A classic block the classic syntax cannot hold
+/// (a `|` in the header, a `\r` or a reserved `--- to` / `<<<` line in the text)
+/// is written as AFB-1.
+pub fn render_block(blk: &PatchBlock) -> String {
+    match blk.dialect {
+        Dialect::Classic if classic_safe(blk) => render_classic(blk),
+        _ => render_armored(blk),
+    }
+}
+
+fn classic_safe(blk: &PatchBlock) -> bool {
+    let header_safe = !blk.file.to_string_lossy().contains('|') && !blk.within.as_ref().is_some_and(|w| w.contains('|'));
+    let text_safe =
+        |text: &str| !text.contains('\r') && text.lines().all(|l| !matches!(l.trim(), "--- to" | "<<<"));
+    header_safe && text_safe(&blk.from) && text_safe(&blk.to)
+}
+
+fn render_classic(blk: &PatchBlock) -> String {
+    let mut header = format!(">>> file: {}", blk.file.display());
+    for (key, value) in options(blk) {
+        header.push_str(&format!(" | {}={}", key.to_ascii_lowercase(), value));
+    }
+    format!("{}\n--- from\n{}\n--- to\n{}\n<<<\n", header, blk.from, blk.to)
+}
+
+fn render_armored(blk: &PatchBlock) -> String {
+    let mut out = String::from("-----BEGIN APPLYDIFF AFB-1-----\n");
+    out.push_str(&format!("Path: {}\n", blk.file.display()));
+    out.push_str(&format!("Fuzz: {}\n", blk.fuzz));
+    for (key, value) in options(blk).into_iter().filter(|(key, _)| *key != "Fuzz") {
+        out.push_str(&format!("{}: {}\n", key, value));
+    }
+    out.push_str("Encoding: base64\nFrom:\n");
+    out.push_str(&wrapped(&encode_base64(blk.from.as_bytes())));
+    out.push_str("To:\n");
+    out.push_str(&wrapped(&encode_base64(blk.to.as_bytes())));
+    out.push_str("-----END APPLYDIFF AFB-1-----\n");
+    out
+}
+
+/// Header options that differ from the defaults, as (AFB-1 name, value)
+fn options(blk: &PatchBlock) -> Vec<(&'static str, String)> {
+    let mut opts = Vec::new();
+    if blk.fuzz != DEFAULT_FUZZ {
+        opts.push(("Fuzz", blk.fuzz.to_string()));
+    }
+    if let Some(margin) = blk.margin {
+        opts.push(("Margin", margin.to_string()));
+    }
+    match blk.occurrence {
+        Occurrence::Unique => {}
+        Occurrence::Nth(n) => opts.push(("Occurrence", n.to_string())),
+        Occurrence::Last => opts.push(("Occurrence", "last".to_string())),
+        Occurrence::All => opts.push(("All", "true".to_string())),
+    }
+    match blk.match_mode {
+        MatchMode::Default => {}
+        MatchMode::ExactOnly => opts.push(("Match", "exact-only".to_string())),
+        MatchMode::NoFuzzy => opts.push(("Match", "no-fuzzy".to_string())),
+    }
+    if let Some(line) = blk.line_hint {
+        opts.push(("Line", line.to_string()));
+    }
+    if let Some(within) = &blk.within {
+        opts.push(("Within", within.clone()));
+    }
+    if blk.create {
+        opts.push(("Create", "true".to_string()));
+    }
+    if let Some(metric) = blk.metric {
+        opts.push(("Metric", metric.to_string()));
+    }
+    opts
+}
+
+fn wrapped(encoded: &str) -> String {
+    encoded.as_bytes().chunks(BASE64_WRAP).map(|c| format!("{}\n", String::from_utf8_lossy(c))).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::render_patch;
+    use crate::parse::{Dialect, MatchMode, Occurrence, Parser};
+
+    #[test]
+    fn rendered_blocks_parse_back() {
+        let patch = ">>> file: a.rs | fuzz=0.7 | occurrence=last | line=12 | metric=weighted\n--- from\nfn a() {}\n\n--- to\nfn b() {}\n<<<\n\n\
+                     -----BEGIN APPLYDIFF AFB-1-----\nPath: b.txt\nFuzz: 0.85\nMatch: no-fuzzy\nEncoding: base64\nFrom:\nYmV0YQo=\nTo:\nQkVUQSEK\n-----END APPLYDIFF AFB-1-----\n";
+        let blocks = Parser::new().parse(patch).unwrap();
+        let mut unsafe_classic = blocks[0].clone();
+        unsafe_classic.to = "x\r\n<<<\n".to_string();
+        let mut all = blocks.clone();
+        all.push(unsafe_classic);
+
+        let again = Parser::new().parse(&render_patch(&all)).unwrap();
+        assert_eq!(again.len(), 3);
+        for (a, b) in all.iter().zip(&again) {
+            assert_eq!((&a.file, &a.from, &a.to, a.fuzz, a.occurrence), (&b.file, &b.from, &b.to, b.fuzz, b.occurrence));
+            assert_eq!((a.match_mode, a.line_hint, a.metric), (b.match_mode, b.line_hint, b.metric));
+        }
+        assert_eq!(again[0].from, "fn a() {}\n");
+        assert_eq!((again[0].dialect, again[1].dialect, again[2].dialect), (Dialect::Classic, Dialect::Armored, Dialect::Armored));
+        assert_eq!((again[0].occurrence, again[1].match_mode), (Occurrence::Last, MatchMode::NoFuzzy));
+    }
+}
@@ -20,6 +20,7 @@ Rules:
 - Base64 may be wrapped arbitrarily; whitespace will be ignored.
 - If you cannot find the exact old text, lower Fuzz (e.g., 0.80) but keep intent.
 - Emit multiple blocks back-to-back for multiple files.
+- A new file whose path looks like a typo of an existing one is refused; to create it anyway, add a `Create: true` line after `Fuzz:`.
 "#;
     prompt.to_string()
 }
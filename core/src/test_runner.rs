@@ -1,13 +1,14 @@
-use crate::apply::Applier;
+use crate::apply::{run_by_file, Applier};
+use crate::config::ProjectConfig;
 use crate::logger::Logger;
-use crate::parse::Parser;
+use crate::r#match::MatchTier;
+use crate::parse::{render_patch, Parser};
 use crate::test_helpers::*;
 use chrono::Local;
 use serde::Deserialize;
-use std::cell::RefCell;
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::rc::Rc;
+use std::sync::{Arc, Mutex};
 
 #[derive(Deserialize, Debug)]
 struct TestMeta {
@@ -106,7 +107,7 @@ fn run_test_case(rid: u64, log: &mut String, case_path: &Path) -> bool {
         }
     };
     
-    let log_buffer = Rc::new(RefCell::new(String::new()));
+    let log_buffer = Arc::new(Mutex::new(String::new()));
     let logger = Logger::new_for_test(rid, Some(log_buffer.clone()));
 
     let parser = Parser::new();
@@ -119,15 +120,36 @@ fn run_test_case(rid: u64, log: &mut String, case_path: &Path) -> bool {
         }
     };
 
-    let applier = Applier::new(&logger, sandbox.clone(), false);
+    let config = match ProjectConfig::load(&sandbox) {
+        Ok(c) => c,
+        Err(e) => {
+            logln(log, format!("  ❌ Project config failed: {}", e));
+            cleanup(&sandbox).ok();
+            return false;
+        }
+    };
+
+    // the engine preview and apply share; cases expecting failures apply the rest
+    let mut run = run_by_file(&blocks, config.parallel, || {
+        Applier::new(&logger, sandbox.clone(), false).with_config(config.clone())
+    });
     let mut ok_count = 0;
     let mut fail_count = 0;
-    for block in &blocks {
-        match applier.apply_block(block) {
-            Ok(_) => ok_count += 1,
+    let mut canonical = Vec::new();
+    for result in &run.results {
+        match result {
+            Ok(r) => {
+                ok_count += 1;
+                canonical.push(r.canonical.clone());
+            }
             Err(_) => fail_count += 1,
         }
     }
+    if let Some(Err(e)) = run.commit(true) {
+        logln(log, format!("    ❌ Writing the patched files failed: {}", e));
+        cleanup(&sandbox).ok();
+        return false;
+    }
 
     let mut checks_passed = true;
 
@@ -142,7 +164,7 @@ fn run_test_case(rid: u64, log: &mut String, case_path: &Path) -> bool {
     }
     
     if let Some(expected_str) = meta.expected_log_contains {
-        if !log_buffer.borrow().contains(&expected_str) {
+        if !log_buffer.lock().unwrap_or_else(|p| p.into_inner()).contains(&expected_str) {
             logln(log, format!("    ❌ Log verification failed. Did not find '{}'.", expected_str));
             checks_passed = false;
         } else {
@@ -156,6 +178,13 @@ fn run_test_case(rid: u64, log: &mut String, case_path: &Path) -> bool {
         checks_passed = false;
     }
 
+    if !canonical.is_empty() {
+        if let Err(e) = verify_canonical_replay(rid, log, &before_dir, &after_dir, config, &render_patch(&canonical)) {
+            logln(log, format!("    ❌ Canonical replay failed: {}", e));
+            checks_passed = false;
+        }
+    }
+
     // Binary CRLF verification for crlf-related tests
     let case_name = case_path.file_name()
         .and_then(|n| n.to_str())
@@ -172,6 +201,39 @@ fn run_test_case(rid: u64, log: &mut String, case_path: &Path) -> bool {
     checks_passed
 }
 
+/// The canonical patch of the blocks that applied must reproduce `after` from a fresh
+/// `before`, every block matching without fuzz.
+fn verify_canonical_replay(
+    rid: u64,
+    log: &mut String,
+    before_dir: &Path,
+    after_dir: &Path,
+    config: ProjectConfig,
+    patch: &str,
+) -> std::result::Result<(), String> {
+    let sandbox = make_sandbox().map_err(|e| format!("sandbox creation failed: {}", e))?;
+    let outcome = (|| {
+        copy_dir_all(before_dir, &sandbox).map_err(|e| format!("failed to copy 'before' state: {}", e))?;
+        let blocks = Parser::new().parse(patch).map_err(|e| format!("canonical patch does not parse: {}", e))?;
+        let logger = Logger::new_for_test(rid, None);
+        let mut run = run_by_file(&blocks, config.parallel, || {
+            Applier::new(&logger, sandbox.clone(), false).with_config(config.clone())
+        });
+        for (i, result) in run.results.iter().enumerate() {
+            let result = result.as_ref().map_err(|e| format!("block {} did not apply: {}", i + 1, e))?;
+            if result.tier != MatchTier::Exact {
+                return Err(format!("block {} matched by {:?}, not exactly", i + 1, result.tier));
+            }
+        }
+        run.commit(false).transpose().map_err(|e| e.to_string())?;
+        verify_dirs_match(log, &sandbox, after_dir).map_err(|e| e.to_string())
+    })();
+    cleanup(&sandbox).ok();
+    outcome?;
+    logln(log, "    ✓ Canonical patch replays exactly");
+    Ok(())
+}
+
 /// Binary verification of line endings at byte level
 fn verify_line_endings_binary(
     log: &mut String,
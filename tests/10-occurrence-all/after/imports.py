@@ -0,0 +1,9 @@
+from modern import helpers
+
+def one():
+    from modern import helpers
+    return helpers.one()
+
+def two():
+    from modern import helpers
+    return helpers.two()
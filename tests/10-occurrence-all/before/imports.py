@@ -0,0 +1,9 @@
+from legacy import helpers
+
+def one():
+    from legacy import helpers
+    return helpers.one()
+
+def two():
+    from legacy import helpers
+    return helpers.two()
@@ -0,0 +1,6 @@
+class Greeter:
+    def greet(self, name):
+        if name:
+            name = name.strip()
+            return "hi " + name
+        return "hi"
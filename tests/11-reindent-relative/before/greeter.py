@@ -0,0 +1,5 @@
+class Greeter:
+    def greet(self, name):
+        if name:
+            return "hi " + name
+        return "hi"
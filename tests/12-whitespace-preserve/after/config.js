@@ -0,0 +1,3 @@
+const HOST    = "localhost";   
+const PORT = 9090;
+const TIMEOUT = 30;
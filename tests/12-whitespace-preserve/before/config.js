@@ -0,0 +1,3 @@
+const HOST    = "localhost";   
+const PORT    = 8080;
+const TIMEOUT = 30;
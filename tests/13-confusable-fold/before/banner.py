@@ -0,0 +1,4 @@
+def banner():
+    title = "Release notes"
+    sep = "--"
+    print(title, sep)
@@ -0,0 +1,20 @@
+import logging
+
+log = logging.getLogger(__name__)
+
+
+def process_batch(items, retries=3):
+    log.info("processing %d items", len(items))
+    results = []
+    for item in items:
+        value = transform(item)
+        if value is None:
+            log.warning("skipping %r", item)
+            continue
+        results.append(value)
+    log.info("processed %d items", len(results))
+    return results
+
+
+def transform(item):
+    return item.strip() or None
@@ -0,0 +1,18 @@
+import { Router } from "express";
+import { listUsers, getUser, createUser } from "./users";
+
+const router = Router();
+
+router.get("/users", listUsers);
+
+// Lookups by id go through the cache layer first; see cache.ts for the
+// eviction policy. Keep this route above the wildcard handler below.
+// TODO(ops): add rate limiting once the gateway supports it.
+
+router.get("/users/:id", getUser);
+
+router.post("/users", createUser);
+
+router.all("*", (_req, res) => res.status(404).end());
+
+export default router;
@@ -0,0 +1,19 @@
+def load_user(db, user_id):
+    row = db.fetch("users", user_id)
+    if row is None:
+        return None
+    return User.from_row(row)
+
+
+def load_team(db, team_id):
+    row = db.fetch("teams", team_id)
+    if row is None:
+        return None
+    return Team.from_row(row)
+
+
+def load_org(db, org_id):
+    row = db.fetch("orgs", org_id)
+    if row is None:
+        return None
+    return Org.from_row(row)
@@ -0,0 +1,9 @@
+def configure_alpha():
+    # First occurrence
+    print("Enabling feature_xyz...")
+    print("Configuring alpha mode")
+
+def configure_beta():
+    # Second occurrence (identical)
+    print("Enabling feature_xyz...")
+    print("Configuring beta mode")
@@ -0,0 +1,18 @@
+export async function withRetry(fn, attempts = 3) {
+  let lastError;
+
+  // Exponential backoff: 100ms, 200ms, 400ms...
+  for (let i = 0; i < attempts; i++) {
+    try {
+      return await fn(); // success short-circuits
+    } catch (err) {
+      lastError = err;
+      if (!isRetryable(err)) break;
+
+      /* Jitter keeps a fleet of clients from
+         retrying in lockstep. */
+      await sleep(100 * 2 ** i + Math.random() * 50);
+    }
+  }
+  throw lastError;
+}
@@ -0,0 +1,14 @@
+function summarize(values) {
+  if (values.length === 0) {
+    return null;
+  }
+  const total = sum(values);
+  return total / values.length;
+}
+
+function report(values) {
+  if (values.length === 0) {
+    return null;
+  const total = sum(values);
+  return total / values.length;
+}
@@ -0,0 +1,3 @@
+def greet(name):
+    message = "Hello, " + name
+    print(message)
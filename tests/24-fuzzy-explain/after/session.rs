@@ -0,0 +1,7 @@
+pub fn open_session(store: &Store, id: &str) -> Result<Session> {
+    let rec = store.load(id)?;
+    let session = Session::from_record(&rec);
+    session.touch();
+    log::debug!("opened session {}", id);
+    Ok(session)
+}
@@ -0,0 +1,6 @@
+pub fn open_session(store: &Store, id: &str) -> Result<Session> {
+    let record = store.load(id)?;
+    let session = Session::from_record(&record);
+    session.touch();
+    Ok(session)
+}
@@ -0,0 +1,9 @@
+pub fn clamp_percent(value: f64) -> f64 {
+    if value < 0.0 {
+        0.0
+    } else if value > 100.0 {
+        100.0
+    } else {
+        value
+    }
+}
@@ -0,0 +1 @@
+pub use super::panel::Panel;
@@ -0,0 +1 @@
+from .lint import check
@@ -0,0 +1,3 @@
+pub struct Panel {
+    pub title: String,
+}
@@ -0,0 +1,16 @@
+def export_report(rows, path, fmt="csv"):
+    """Write rows to path and record the export.
+
+    Rows are written in id order; the format defaults to CSV.
+    """
+    rows = sorted(rows, key=lambda r: r.id)
+    header = ["id", "name", "total", "updated"]
+    if not rows:
+        raise ValueError("no rows to export")
+    with open(path, "w", newline="") as fh:
+        writer = make_writer(fh, fmt)
+        writer.writerow(header)
+        for row in rows:
+            writer.writerow([row.id, row.name, row.total, row.updated])
+    audit.record("export", path=path, count=len(rows))
+    return path
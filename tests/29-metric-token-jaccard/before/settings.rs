@@ -0,0 +1,23 @@
+use crate::net::{DEFAULT_ENDPOINT, VERSION};
+
+pub struct Settings {
+    pub retries: u32,
+    pub timeout_ms: u64,
+    pub verbose: bool,
+    pub endpoint: String,
+    pub user_agent: String,
+}
+
+pub fn default_settings() -> Settings {
+    Settings {
+        retries: 3,
+        timeout_ms: 5_000,
+        verbose: false,
+        endpoint: DEFAULT_ENDPOINT.to_string(),
+        user_agent: format!("applydiff/{}", VERSION),
+    }
+}
+
+pub fn quiet(settings: Settings) -> Settings {
+    Settings { verbose: false, ..settings }
+}
@@ -0,0 +1,6 @@
+export async function fetchUser(id, opts = {}) {
+  const res = await request('GET', `/users/${id}`, { timeout: opts.timeout ?? 5000 });
+  if (res.status === 404) return null;
+  if (!res.ok) throw new Error(`fetchUser ${id}: ${res.status}`);
+  return { id: res.body.id, name: res.body.name, roles: res.body.roles ?? [] };
+}
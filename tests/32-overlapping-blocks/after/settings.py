@@ -0,0 +1,6 @@
+TIMEOUT = 60
+RETRIES = 3
+
+
+def connect(host):
+    return open_socket(host, TIMEOUT, retries=RETRIES)